@@ -0,0 +1,8 @@
+-- The initial seed stored a hash that does not verify against its documented
+-- password, so the default admin could never log in. Reset it to a valid
+-- bcrypt hash of 'admin123', but only if it was never changed.
+UPDATE users
+SET password_hash = '$2b$12$Ur0ljDZnCyGs46Jjb0ZUSOXzLId3KVvbIdGzMs8LBK/3dGq9BnS5C',
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = '00000000-0000-0000-0000-000000000001'
+  AND password_hash = '$2b$12$LQv3c1yqBWVHxkd0LHAkCOYz6TtxMQJqhN8/LewYw2Ah/L4xIJQVW';
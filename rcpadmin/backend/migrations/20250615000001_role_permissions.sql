@@ -0,0 +1,36 @@
+-- Permissions granted to each role, checked per route by the API
+CREATE TABLE role_permissions (
+    role TEXT NOT NULL CHECK (role IN ('Admin', 'Operator', 'Viewer')),
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+-- Admins can do everything
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Admin', 'server:read'),
+    ('Admin', 'server:restart'),
+    ('Admin', 'server:config'),
+    ('Admin', 'apps:read'),
+    ('Admin', 'apps:manage'),
+    ('Admin', 'apps:launch'),
+    ('Admin', 'sessions:read'),
+    ('Admin', 'sessions:terminate'),
+    ('Admin', 'system:read'),
+    ('Admin', 'users:manage'),
+    ('Admin', 'roles:manage');
+
+-- Operators run applications and manage sessions
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Operator', 'server:read'),
+    ('Operator', 'apps:read'),
+    ('Operator', 'apps:launch'),
+    ('Operator', 'sessions:read'),
+    ('Operator', 'sessions:terminate'),
+    ('Operator', 'system:read');
+
+-- Viewers are read-only
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Viewer', 'server:read'),
+    ('Viewer', 'apps:read'),
+    ('Viewer', 'sessions:read'),
+    ('Viewer', 'system:read');
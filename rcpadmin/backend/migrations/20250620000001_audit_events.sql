@@ -0,0 +1,17 @@
+-- Record of every mutating admin API call and every login attempt
+CREATE TABLE audit_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    occurred_at DATETIME NOT NULL,
+    user_id TEXT,
+    username TEXT,
+    action TEXT NOT NULL,
+    resource TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('success', 'failure')),
+    status_code INTEGER NOT NULL,
+    source_ip TEXT
+);
+
+CREATE INDEX idx_audit_events_occurred_at ON audit_events(occurred_at);
+CREATE INDEX idx_audit_events_username ON audit_events(username);
+
+INSERT INTO role_permissions (role, permission) VALUES ('Admin', 'audit:read');
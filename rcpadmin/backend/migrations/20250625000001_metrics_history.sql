@@ -0,0 +1,32 @@
+-- Historical system and per-session metrics. `sampled_at` is a unix timestamp
+-- in seconds; `resolution` is 0 for raw samples and otherwise the width in
+-- seconds of the bucket a downsampled row averages.
+CREATE TABLE metrics_samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    resolution INTEGER NOT NULL,
+    sampled_at INTEGER NOT NULL,
+    cpu_usage REAL NOT NULL,
+    memory_usage INTEGER NOT NULL,
+    total_memory INTEGER NOT NULL,
+    disk_usage INTEGER NOT NULL,
+    total_disk INTEGER NOT NULL,
+    network_rx INTEGER NOT NULL,
+    network_tx INTEGER NOT NULL,
+    active_sessions INTEGER NOT NULL
+);
+
+CREATE INDEX idx_metrics_samples_resolution_time ON metrics_samples(resolution, sampled_at);
+
+CREATE TABLE session_metrics_samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    resolution INTEGER NOT NULL,
+    sampled_at INTEGER NOT NULL,
+    cpu_usage REAL NOT NULL,
+    memory_usage INTEGER NOT NULL,
+    network_rx INTEGER NOT NULL,
+    network_tx INTEGER NOT NULL
+);
+
+CREATE INDEX idx_session_metrics_samples_session ON session_metrics_samples(session_id, resolution, sampled_at);
+CREATE INDEX idx_session_metrics_samples_resolution_time ON session_metrics_samples(resolution, sampled_at);
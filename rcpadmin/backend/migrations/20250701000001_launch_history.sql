@@ -0,0 +1,18 @@
+-- Every application launch made through the admin API and how it ended
+CREATE TABLE launch_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    application_id TEXT NOT NULL,
+    user_id TEXT,
+    username TEXT,
+    arguments TEXT NOT NULL, -- JSON array of strings
+    session_id TEXT,
+    status TEXT NOT NULL CHECK (status IN ('running', 'stopped', 'exited', 'failed')),
+    exit_code INTEGER,
+    error TEXT,
+    launched_at DATETIME NOT NULL,
+    ended_at DATETIME,
+    duration_ms INTEGER
+);
+
+CREATE INDEX idx_launch_history_application ON launch_history(application_id, launched_at);
+CREATE INDEX idx_launch_history_session ON launch_history(session_id);
@@ -0,0 +1,10 @@
+-- Failed login counters, one row per client IP and per username. A row is
+-- removed by a successful login or an admin unlock.
+CREATE TABLE login_failures (
+    scope TEXT NOT NULL CHECK (scope IN ('ip', 'username')),
+    subject TEXT NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failure_at DATETIME NOT NULL,
+    blocked_until DATETIME,
+    PRIMARY KEY (scope, subject)
+);
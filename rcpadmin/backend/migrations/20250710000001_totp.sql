@@ -0,0 +1,37 @@
+-- TOTP (RFC 6238) second factor. A secret stays pending until the user
+-- confirms it with a first code, which sets `enabled_at`.
+CREATE TABLE user_totp (
+    user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL, -- base32
+    enabled_at DATETIME,
+    last_used_step INTEGER, -- time step of the last accepted code; older ones are replays
+    created_at DATETIME NOT NULL
+);
+
+-- One-time recovery codes, for when the authenticator is lost. Only a
+-- SHA-256 hash of each code is stored.
+CREATE TABLE totp_recovery_codes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at DATETIME
+);
+
+CREATE INDEX idx_totp_recovery_codes_user_id ON totp_recovery_codes(user_id);
+
+-- Logins whose password checked out but whose second factor is pending,
+-- keyed by a SHA-256 hash of the challenge token.
+CREATE TABLE login_challenges (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at DATETIME NOT NULL
+);
+
+-- Per-role security settings
+CREATE TABLE role_settings (
+    role TEXT PRIMARY KEY CHECK (role IN ('Admin', 'Operator', 'Viewer')),
+    require_totp BOOLEAN NOT NULL DEFAULT 0
+);
+
+INSERT INTO role_settings (role) VALUES ('Admin'), ('Operator'), ('Viewer');
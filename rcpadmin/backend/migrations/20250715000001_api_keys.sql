@@ -0,0 +1,20 @@
+-- Long-lived keys for automation, sent as `Authorization: ApiKey <key>`.
+-- Only a SHA-256 hash of each key is stored; `key_prefix` identifies it in
+-- listings. A key acts for the user who created it, limited to its own
+-- permissions (a JSON array).
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    permissions TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    last_used_at DATETIME,
+    revoked_at DATETIME
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+
+INSERT INTO role_permissions (role, permission) VALUES ('Admin', 'apikeys:manage');
@@ -0,0 +1,11 @@
+-- Access tokens issued before this were cut off by a password change.
+ALTER TABLE users ADD COLUMN password_changed_at DATETIME;
+
+-- Single-use password reset tokens handed out by an admin, keyed by a
+-- SHA-256 hash of the token. A user has at most one; using it deletes it.
+CREATE TABLE password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    expires_at DATETIME NOT NULL,
+    created_at DATETIME NOT NULL
+);
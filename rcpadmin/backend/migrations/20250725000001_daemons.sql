@@ -0,0 +1,34 @@
+-- The rcpdaemon instances this backend manages. `token` is the bearer token
+-- the daemon expects; it has to be sent as is, so it is stored as is, and
+-- the API never returns it.
+CREATE TABLE daemons (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    url TEXT NOT NULL,
+    token TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    health TEXT NOT NULL DEFAULT 'unknown' CHECK (health IN ('unknown', 'healthy', 'unreachable', 'error')),
+    health_error TEXT,
+    last_checked_at DATETIME,
+    last_healthy_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The single daemon of earlier versions. An empty URL is filled in from the
+-- [rcpdaemon] settings at the next start.
+INSERT INTO daemons (id, name, url) VALUES ('00000000-0000-0000-0000-000000000001', 'default', '');
+
+-- Everything recorded so far came from that daemon
+ALTER TABLE metrics_samples ADD COLUMN daemon_id TEXT;
+UPDATE metrics_samples SET daemon_id = '00000000-0000-0000-0000-000000000001';
+CREATE INDEX idx_metrics_samples_daemon ON metrics_samples(daemon_id, resolution, sampled_at);
+
+ALTER TABLE session_metrics_samples ADD COLUMN daemon_id TEXT;
+UPDATE session_metrics_samples SET daemon_id = '00000000-0000-0000-0000-000000000001';
+
+ALTER TABLE launch_history ADD COLUMN daemon_id TEXT;
+UPDATE launch_history SET daemon_id = '00000000-0000-0000-0000-000000000001';
+CREATE INDEX idx_launch_history_daemon_application ON launch_history(daemon_id, application_id, launched_at);
+
+INSERT INTO role_permissions (role, permission) VALUES ('Admin', 'daemons:manage');
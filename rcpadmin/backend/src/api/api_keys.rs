@@ -0,0 +1,75 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::Json,
+    routing::{delete, get},
+    Extension, Router,
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    auth::{require_permission, Claims, GrantedPermissions},
+    error::{AppError, Result},
+    models::{ApiKey, CreateApiKey, CreatedApiKey, Permission},
+    services::api_key::ApiKeyService,
+    AppState,
+};
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_api_keys).post(create_api_key))
+        .route("/:id", delete(revoke_api_key))
+        .route_layer(from_fn_with_state(
+            Permission::ApiKeysManage,
+            require_permission,
+        ))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/api-keys", tag = "api-keys",
+    responses((status = 200, body = [ApiKey]))
+)]
+async fn list_api_keys(State(state): State<AppState>) -> Result<Json<Vec<ApiKey>>> {
+    let keys = ApiKeyService::new(state.db.clone()).list().await?;
+    Ok(Json(keys))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/api-keys", tag = "api-keys",
+    request_body = CreateApiKey,
+    responses((status = 201, body = CreatedApiKey))
+)]
+async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Extension(granted): Extension<GrantedPermissions>,
+    Json(request): Json<CreateApiKey>,
+) -> Result<(StatusCode, Json<CreatedApiKey>)> {
+    let owner_id =
+        Uuid::parse_str(&claims.sub).map_err(|_| AppError::Auth("Invalid token".to_string()))?;
+
+    let (key, api_key) = ApiKeyService::new(state.db.clone())
+        .create(owner_id, &granted.0, &request)
+        .await?;
+    info!(
+        "User '{}' created API key '{}' ({})",
+        claims.username, api_key.name, api_key.prefix
+    );
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { key, api_key })))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/api-keys/{id}", tag = "api-keys",
+    params(("id" = Uuid, Path, description = "API key id")),
+    responses((status = 200, body = ApiKey))
+)]
+async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiKey>> {
+    let api_key = ApiKeyService::new(state.db.clone()).revoke(id).await?;
+    info!("Revoked API key '{}' ({})", api_key.name, api_key.prefix);
+    Ok(Json(api_key))
+}
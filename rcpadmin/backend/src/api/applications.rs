@@ -1,29 +1,473 @@
-use axum::{extract::State, response::Json, routing::get, Router};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::Json,
+    routing::{get, post, put},
+    Extension, Router,
+};
 use serde_json::Value;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
 
-use crate::{error::Result, AppState};
+use crate::{
+    auth::{require_permission, Claims},
+    error::{AppError, FieldError, Result},
+    fleet::DaemonHandle,
+    models::{
+        Application, CreateApplication, LaunchApplication, LaunchHistory, LaunchHistoryQuery,
+        LaunchRecord, Permission, SavedApplication,
+    },
+    services::launch_history::{LaunchHistoryService, NewLaunch},
+    AppState,
+};
 
 pub fn create_routes() -> Router<AppState> {
-    Router::new()
+    let read = Router::new()
         .route("/", get(get_applications))
         .route("/:id", get(get_application))
-        // .route("/", post(create_application))
-        // .route("/:id", put(update_application))
-        // .route("/:id", delete(delete_application))
+        .route("/:id/launches", get(get_launch_history))
+        .route_layer(from_fn_with_state(Permission::AppsRead, require_permission));
+
+    let launch = Router::new()
+        .route("/:id/launch", post(launch_application))
+        .route("/:id/stop", post(stop_application))
+        .route_layer(from_fn_with_state(
+            Permission::AppsLaunch,
+            require_permission,
+        ));
+
+    let manage = Router::new()
+        .route("/", post(create_application))
+        .route("/:id", put(update_application).delete(delete_application))
+        .route_layer(from_fn_with_state(
+            Permission::AppsManage,
+            require_permission,
+        ));
+
+    read.merge(manage).merge(launch)
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/applications", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = [Application]))
+)]
+async fn get_applications(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+) -> Result<Json<Vec<Application>>> {
+    let applications = daemon.client.get_applications().await?;
+    Ok(Json(applications))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/applications/{id}", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id")),
+    responses((status = 200, body = Application))
+)]
+async fn get_application(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, String)>,
+) -> Result<Json<Application>> {
+    let application = daemon.client.get_application(&id).await?;
+    Ok(Json(application))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/daemons/{daemon_id}/applications", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    request_body = CreateApplication,
+    responses((status = 201, body = SavedApplication))
+)]
+async fn create_application(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Json(app): Json<CreateApplication>,
+) -> Result<(StatusCode, Json<SavedApplication>)> {
+    let unverified = validate_application(&daemon, &app, None).await?;
+
+    let application = daemon.client.create_application(app).await?;
+    info!(
+        "Created application {} on RCP daemon '{}'",
+        application.name, daemon.name
+    );
+    Ok((
+        StatusCode::CREATED,
+        Json(SavedApplication {
+            application,
+            unverified,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    put, path = "/api/v1/daemons/{daemon_id}/applications/{id}", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id")),
+    request_body = CreateApplication,
+    responses((status = 200, body = SavedApplication))
+)]
+async fn update_application(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, String)>,
+    Json(app): Json<CreateApplication>,
+) -> Result<Json<SavedApplication>> {
+    let unverified = validate_application(&daemon, &app, Some(&id)).await?;
+
+    let application = daemon.client.update_application(&id, app).await?;
+    info!(
+        "Updated application {} on RCP daemon '{}'",
+        application.name, daemon.name
+    );
+    Ok(Json(SavedApplication {
+        application,
+        unverified,
+    }))
 }
 
-async fn get_applications(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
+#[utoipa::path(
+    delete, path = "/api/v1/daemons/{daemon_id}/applications/{id}", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id")),
+    responses((status = 200, body = Value))
+)]
+async fn delete_application(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, String)>,
+) -> Result<Json<serde_json::Value>> {
+    daemon.client.delete_application(&id).await?;
+    info!("Deleted application {} on RCP daemon '{}'", id, daemon.name);
     Ok(Json(serde_json::json!({
-        "applications": [],
-        "message": "RCP daemon not connected - mock response"
+        "message": format!("Application {} deleted successfully", id),
+        "success": true
     })))
 }
 
-async fn get_application(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
+/// Launches an application on the daemon and records the attempt, including
+/// failed ones, in the launch history.
+#[utoipa::path(
+    post, path = "/api/v1/daemons/{daemon_id}/applications/{id}/launch", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id")),
+    request_body(content = Option<LaunchApplication>),
+    responses((status = 201, body = LaunchRecord))
+)]
+async fn launch_application(
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Extension(claims): Extension<Claims>,
+    Path((_, id)): Path<(Uuid, String)>,
+    body: Option<Json<LaunchApplication>>,
+) -> Result<(StatusCode, Json<LaunchRecord>)> {
+    let arguments = match body.and_then(|Json(body)| body.arguments) {
+        Some(arguments) => arguments,
+        None => daemon.client.get_application(&id).await?.arguments,
+    };
+
+    let result = daemon.client.launch_application(&id, &arguments).await;
+    let outcome = match &result {
+        Ok(response) => Ok(response
+            .get("session_id")
+            .and_then(Value::as_str)
+            .and_then(|session_id| Uuid::parse_str(session_id).ok())),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let record = LaunchHistoryService::new(state.db.clone())
+        .record_launch(NewLaunch {
+            daemon_id: daemon.id,
+            application_id: id.clone(),
+            user_id: Some(claims.sub.clone()),
+            username: Some(claims.username.clone()),
+            arguments,
+            outcome,
+        })
+        .await?;
+
+    result?;
+    info!(
+        "User '{}' launched application {} on RCP daemon '{}'",
+        claims.username, id, daemon.name
+    );
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/daemons/{daemon_id}/applications/{id}/stop", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id")),
+    responses((status = 200, body = Value, description = "`{ message, success, exit_code, launch }`"))
+)]
+async fn stop_application(
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Extension(claims): Extension<Claims>,
+    Path((_, id)): Path<(Uuid, String)>,
+) -> Result<Json<Value>> {
+    let response = daemon.client.stop_application(&id).await?;
+    let exit_code = response.get("exit_code").and_then(Value::as_i64);
+
+    let launch = LaunchHistoryService::new(state.db.clone())
+        .record_stop(daemon.id, &id, exit_code)
+        .await?;
+    info!(
+        "User '{}' stopped application {} on RCP daemon '{}'",
+        claims.username, id, daemon.name
+    );
+
     Ok(Json(serde_json::json!({
-        "application": null,
-        "message": "RCP daemon not connected - mock response"
+        "message": format!("Application {} stopped", id),
+        "success": true,
+        "exit_code": exit_code,
+        "launch": launch
     })))
 }
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/applications/{id}/launches", tag = "applications",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = String, Path, description = "Application id"), LaunchHistoryQuery),
+    responses((status = 200, body = LaunchHistory))
+)]
+async fn get_launch_history(
+    State(state): State<AppState>,
+    Path((daemon_id, id)): Path<(Uuid, String)>,
+    Query(query): Query<LaunchHistoryQuery>,
+) -> Result<Json<LaunchHistory>> {
+    let history = LaunchHistoryService::new(state.db.clone())
+        .list(daemon_id, &id, &query)
+        .await?;
+    Ok(Json(history))
+}
+
+/// Checks an application definition before it is sent to the daemon,
+/// reporting every problem at once. `existing_id` is the application being
+/// updated, which may keep its own name. Returns the fields the daemon could
+/// not check.
+async fn validate_application(
+    daemon: &DaemonHandle,
+    app: &CreateApplication,
+    existing_id: Option<&str>,
+) -> Result<Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    let name = app.name.trim();
+    if name.is_empty() {
+        errors.push(FieldError::new("name", "Name is required"));
+    }
+    if app.launch_command.trim().is_empty() {
+        errors.push(FieldError::new(
+            "launch_command",
+            "Launch command is required",
+        ));
+    }
+
+    for key in app.environment.keys() {
+        if !is_valid_env_key(key) {
+            errors.push(FieldError::new(
+                format!("environment.{}", key),
+                "Variable names must start with a letter or underscore and contain only letters, digits and underscores",
+            ));
+        }
+    }
+
+    let path = app.path.trim();
+    let (applications, path_exists) = tokio::join!(daemon.client.get_applications(), async {
+        if path.is_empty() {
+            Ok(None)
+        } else {
+            daemon.client.path_exists(path).await
+        }
+    });
+
+    // Daemons without the path check still accept the application; the
+    // response says the path was not checked
+    let mut unverified = Vec::new();
+    if path.is_empty() {
+        errors.push(FieldError::new("path", "Path is required"));
+    } else {
+        match path_exists? {
+            Some(true) => {}
+            Some(false) => errors.push(FieldError::new(
+                "path",
+                format!("'{}' does not exist on the daemon host", path),
+            )),
+            None => unverified.push(FieldError::new(
+                "path",
+                "Not checked: the daemon cannot report whether the path exists",
+            )),
+        }
+    }
+
+    if !name.is_empty() {
+        let duplicate = applications?.iter().any(|existing| {
+            existing.name.eq_ignore_ascii_case(name) && Some(existing.id.as_str()) != existing_id
+        });
+        if duplicate {
+            errors.push(FieldError::new(
+                "name",
+                format!("An application named '{}' already exists", name),
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(unverified)
+    } else {
+        Err(AppError::InvalidFields(errors))
+    }
+}
+
+/// POSIX-style environment variable name: `[A-Za-z_][A-Za-z0-9_]*`.
+fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::rcpdaemon::{
+        api::{self, PathExists, PathQuery},
+        DaemonClientOptions, RcpDaemonClient,
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    /// A daemon with one application, `calc`, where only paths under
+    /// `/opt/` exist. Without `fs_check` it predates the path check.
+    async fn daemon(fs_check: bool) -> DaemonHandle {
+        let calc = Application {
+            id: "a1".to_string(),
+            name: "calc".to_string(),
+            version: "1.0".to_string(),
+            description: String::new(),
+            path: "/opt/calc".to_string(),
+            launch_command: "calc".to_string(),
+            arguments: Vec::new(),
+            environment: HashMap::new(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let mut router = Router::new().route(
+            api::APPS,
+            get(move || {
+                let calc = calc.clone();
+                async move { Json(vec![calc]) }
+            }),
+        );
+        if fs_check {
+            router = router.route(
+                api::FS_EXISTS,
+                get(|Query(query): Query<PathQuery>| async move {
+                    Json(PathExists {
+                        exists: query.path.starts_with("/opt/"),
+                    })
+                }),
+            );
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        DaemonHandle {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            enabled: true,
+            client: RcpDaemonClient::new(&url, DaemonClientOptions::default()).unwrap(),
+        }
+    }
+
+    fn app(name: &str, path: &str, command: &str) -> CreateApplication {
+        CreateApplication {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            description: String::new(),
+            path: path.to_string(),
+            launch_command: command.to_string(),
+            arguments: Vec::new(),
+            environment: HashMap::new(),
+        }
+    }
+
+    fn fields(result: Result<Vec<FieldError>>) -> Vec<String> {
+        match result {
+            Err(AppError::InvalidFields(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            other => panic!("expected field errors, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_a_valid_application() {
+        let daemon = daemon(true).await;
+        let unverified =
+            validate_application(&daemon, &app("editor", "/opt/editor", "editor"), None)
+                .await
+                .unwrap();
+        assert!(unverified.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_every_missing_field() {
+        let daemon = daemon(true).await;
+        let result = validate_application(&daemon, &app(" ", "", " "), None).await;
+        assert_eq!(fields(result), ["name", "launch_command", "path"]);
+    }
+
+    #[tokio::test]
+    async fn refuses_a_path_missing_on_the_daemon_host() {
+        let daemon = daemon(true).await;
+        let result =
+            validate_application(&daemon, &app("editor", "/usr/bin/editor", "editor"), None).await;
+        assert_eq!(fields(result), ["path"]);
+    }
+
+    #[tokio::test]
+    async fn reports_the_path_unverified_on_older_daemons() {
+        let daemon = daemon(false).await;
+        let unverified =
+            validate_application(&daemon, &app("editor", "/usr/bin/editor", "editor"), None)
+                .await
+                .unwrap();
+        assert_eq!(unverified.len(), 1);
+        assert_eq!(unverified[0].field, "path");
+    }
+
+    #[tokio::test]
+    async fn refuses_bad_environment_keys() {
+        let daemon = daemon(true).await;
+        let mut editor = app("editor", "/opt/editor", "editor");
+        for key in ["HOME_DIR", "_private", "1ST", "WITH-DASH", ""] {
+            editor.environment.insert(key.to_string(), "x".to_string());
+        }
+        let mut result = fields(validate_application(&daemon, &editor, None).await);
+        result.sort();
+        assert_eq!(
+            result,
+            ["environment.", "environment.1ST", "environment.WITH-DASH"]
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_duplicate_names_except_the_application_itself() {
+        let daemon = daemon(true).await;
+        let calc = app("CALC", "/opt/calc", "calc");
+
+        let result = validate_application(&daemon, &calc, None).await;
+        assert_eq!(fields(result), ["name"]);
+        assert!(validate_application(&daemon, &calc, Some("a1"))
+            .await
+            .is_ok());
+        let result = validate_application(&daemon, &calc, Some("a2")).await;
+        assert_eq!(fields(result), ["name"]);
+    }
+
+    #[test]
+    fn env_keys_follow_posix_names() {
+        for key in ["PATH", "_", "a1_B2"] {
+            assert!(is_valid_env_key(key), "{}", key);
+        }
+        for key in ["", "1A", "A-B", "A B", "É"] {
+            assert!(!is_valid_env_key(key), "{}", key);
+        }
+    }
+}
@@ -1,72 +1,571 @@
 use axum::{
-    extract::State,
-    response::Json,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
+use chrono::{DateTime, Utc};
+use std::net::SocketAddr;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    auth::generate_token,
+    audit::AuditActor,
+    auth::authorize,
+    error::FieldError,
     error::{AppError, Result},
-    models::{LoginRequest, LoginResponse, User, UserInfo, UserRole},
+    models::{
+        ChangePasswordRequest, LoginOutcome, LoginRequest, LoginResponse, LogoutRequest,
+        OtpChallenge, RefreshRequest, ResetPasswordRequest, TotpCodeRequest, TotpEnrollment,
+        TotpRecoveryCodes, TotpStatus, User, UserInfo, VerifyOtpRequest,
+    },
+    services::{
+        audit::{AuditService, NewAuditEvent},
+        auth::{AuthService, TokenPair},
+        login_throttle::{Lockout, LoginThrottleService, ThrottleScope},
+        password::{verify_password, PasswordService},
+        permission::PermissionService,
+        totp::TotpService,
+    },
     AppState,
 };
 
 pub fn create_routes() -> Router<AppState> {
     Router::new()
         .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
         .route("/validate", get(validate_token))
+        .route("/password", post(change_password))
+        .route("/password/reset", post(reset_password))
+        .route("/verify-otp", post(verify_otp))
+        .route("/totp", get(get_totp_status))
+        .route("/totp/enroll", post(enroll_totp))
+        .route("/totp/confirm", post(confirm_totp))
+        .route("/totp/recovery-codes", post(regenerate_recovery_codes))
+        .route("/totp/disable", post(disable_totp))
 }
 
+#[utoipa::path(
+    post, path = "/api/v1/auth/login", tag = "auth", security(()),
+    request_body = LoginRequest,
+    responses(
+        (status = 200, body = LoginOutcome,
+            description = "Tokens, or a challenge for `verify-otp` if two-factor authentication is enabled"),
+        (status = 429, body = crate::error::ErrorBody, description = "Too many failed attempts",
+            headers(("Retry-After" = i64, description = "Seconds until the next attempt is allowed")))
+    )
+)]
 async fn login(
     State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
     Json(login): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>> {
-    // For simplicity in this example, we'll use a hardcoded admin user
-    // In a real application, fetch from the database
-
-    // Check if we have a hardcoded user match
-    if login.username == "admin" {
-        // Check password (hardcoded for example, use proper password hashing in production)
-        if login.password == "rcpadmin" {
-            // Create a mock user
-            let user = User {
-                id: Uuid::new_v4(),
-                username: "admin".to_string(),
-                email: "admin@example.com".to_string(),
-                password_hash: "".to_string(), // Not needed for this flow
-                role: UserRole::Admin,
-                is_active: true,
-                created_at: chrono::Utc::now(),
-                updated_at: chrono::Utc::now(),
-            };
-
-            // Generate JWT token
-            let token = generate_token(&user, &state.config.jwt_secret, 7)?;
-
-            // Return user info and token
-            return Ok(Json(LoginResponse {
-                token,
-                user: UserInfo {
-                    id: user.id,
-                    username: user.username,
-                    email: user.email,
-                    role: user.role,
-                    is_active: user.is_active,
-                },
-            }));
+) -> Response {
+    // Attribute the attempt in the audit log even when it fails
+    let mut actor = AuditActor {
+        user_id: None,
+        username: Some(login.username.clone()),
+    };
+
+    let result = authenticate(&state, &login, &remote.ip().to_string()).await;
+
+    match result {
+        Ok((user_id, outcome)) => {
+            // A pending second factor is counted once `verify-otp` settles it
+            if let LoginOutcome::Authenticated(_) = outcome {
+                state.telemetry.record_login(true);
+            }
+            actor.user_id = Some(user_id.to_string());
+            (Extension(actor), Json(outcome)).into_response()
+        }
+        Err(err) => {
+            state.telemetry.record_login(false);
+            warn!("Failed login attempt for '{}': {}", login.username, err);
+            (Extension(actor), err).into_response()
+        }
+    }
+}
+
+async fn authenticate(
+    state: &AppState,
+    login: &LoginRequest,
+    ip: &str,
+) -> Result<(Uuid, LoginOutcome)> {
+    let throttle = LoginThrottleService::new(state.db.clone(), &state.config.auth.lockout);
+    let now = Utc::now();
+
+    // Refuse before checking the password, so a blocked client learns nothing
+    if let Some(retry_after) = throttle.retry_after(Some(ip), &login.username, now).await? {
+        return Err(AppError::TooManyAttempts(retry_after));
+    }
+
+    let auth_service = AuthService::new(state.db.clone());
+    let user = match auth_service.authenticate(login).await {
+        Ok(user) => user,
+        Err(AppError::InvalidCredentials) => {
+            // Unknown usernames are counted too, so lockouts do not reveal
+            // which accounts exist
+            record_failed_attempt(state, &throttle, ip, &login.username, now).await?;
+            return Err(AppError::InvalidCredentials);
         }
+        Err(err) => return Err(err),
+    };
+
+    let totp = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer);
+    if totp.is_enabled(user.id).await? {
+        let (challenge_token, expires_in) = totp.create_challenge(user.id, now).await?;
+        info!(
+            "User '{}' passed the password check, awaiting code",
+            user.username
+        );
+        return Ok((
+            user.id,
+            LoginOutcome::OtpRequired(OtpChallenge {
+                otp_required: true,
+                challenge_token,
+                expires_in,
+            }),
+        ));
+    }
+
+    throttle.record_success(Some(ip), &login.username).await?;
+    let tokens = auth_service.issue_tokens(&user, &state.config.auth).await?;
+    info!("User '{}' logged in", user.username);
+
+    Ok((
+        user.id,
+        LoginOutcome::Authenticated(login_response(user, tokens)),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/verify-otp", tag = "auth", security(()),
+    request_body = VerifyOtpRequest,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 429, body = crate::error::ErrorBody, description = "Too many failed attempts",
+            headers(("Retry-After" = i64, description = "Seconds until the next attempt is allowed")))
+    )
+)]
+async fn verify_otp(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    Json(request): Json<VerifyOtpRequest>,
+) -> Response {
+    let mut actor = AuditActor::default();
+
+    let result = complete_login(&state, &request, &remote.ip().to_string(), &mut actor).await;
+    state.telemetry.record_login(result.is_ok());
+
+    match result {
+        Ok(response) => (Extension(actor), Json(response)).into_response(),
+        Err(err) => {
+            warn!(
+                "Failed two-factor login for '{}': {}",
+                actor.username.as_deref().unwrap_or("unknown"),
+                err
+            );
+            (Extension(actor), err).into_response()
+        }
+    }
+}
+
+/// Second login step: trades a challenge from `login` and a code for tokens.
+async fn complete_login(
+    state: &AppState,
+    request: &VerifyOtpRequest,
+    ip: &str,
+    actor: &mut AuditActor,
+) -> Result<LoginResponse> {
+    let totp = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer);
+    let now = Utc::now();
+
+    let user_id = totp.use_challenge(&request.challenge_token, now).await?;
+    let auth_service = AuthService::new(state.db.clone());
+    let user = auth_service
+        .get_user_by_id(user_id)
+        .await
+        .map_err(|err| match err {
+            AppError::NotFound(_) => {
+                AppError::AccountDisabled("Account is disabled or no longer exists".to_string())
+            }
+            other => other,
+        })?;
+    actor.user_id = Some(user.id.to_string());
+    actor.username = Some(user.username.clone());
+
+    if !check_otp(state, &user, ip, &request.code, now).await? {
+        return Err(AppError::InvalidOtp);
+    }
+    totp.finish_challenge(&request.challenge_token).await?;
+
+    let tokens = auth_service.issue_tokens(&user, &state.config.auth).await?;
+    info!(
+        "User '{}' logged in with two-factor authentication",
+        user.username
+    );
+
+    Ok(login_response(user, tokens))
+}
+
+/// Checks a two-factor code against the login throttle: refused outright
+/// while blocked, and a wrong code counts as a failed login.
+async fn check_otp(
+    state: &AppState,
+    user: &User,
+    ip: &str,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let throttle = LoginThrottleService::new(state.db.clone(), &state.config.auth.lockout);
+    if let Some(retry_after) = throttle.retry_after(Some(ip), &user.username, now).await? {
+        return Err(AppError::TooManyAttempts(retry_after));
+    }
+
+    let valid = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .verify(user, code, now)
+        .await?;
+    if valid {
+        throttle.record_success(Some(ip), &user.username).await?;
+    } else {
+        record_failed_attempt(state, &throttle, ip, &user.username, now).await?;
+    }
+    Ok(valid)
+}
+
+async fn record_failed_attempt(
+    state: &AppState,
+    throttle: &LoginThrottleService,
+    ip: &str,
+    username: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    for lockout in throttle.record_failure(Some(ip), username, now).await? {
+        record_lockout(state, lockout).await;
+    }
+    Ok(())
+}
+
+fn login_response(user: User, tokens: TokenPair) -> LoginResponse {
+    LoginResponse {
+        token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
+        user: user.into(),
+    }
+}
+
+/// Lockouts get their own audit event; the failed attempts that led to them
+/// are already recorded as failed logins.
+async fn record_lockout(state: &AppState, lockout: Lockout) {
+    warn!(
+        "Locked out {} '{}' until {} after repeated failed logins",
+        lockout.scope.as_str(),
+        lockout.subject,
+        lockout.until
+    );
+
+    let (username, source_ip) = match lockout.scope {
+        ThrottleScope::Username => (Some(lockout.subject.clone()), None),
+        ThrottleScope::Ip => (None, Some(lockout.subject.clone())),
+    };
+    let event = NewAuditEvent {
+        user_id: None,
+        username,
+        action: "LOCKOUT".to_string(),
+        resource: format!("{}:{}", lockout.scope.as_str(), lockout.subject),
+        success: false,
+        status_code: StatusCode::TOO_MANY_REQUESTS.as_u16(),
+        source_ip,
+    };
+    if let Err(e) = AuditService::new(state.db.clone()).record(event).await {
+        error!("Failed to record audit event: {}", e);
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/refresh", tag = "auth", security(()),
+    request_body = RefreshRequest,
+    responses((status = 200, body = LoginResponse))
+)]
+async fn refresh(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<(Extension<AuditActor>, Json<LoginResponse>)> {
+    let (user, tokens) = AuthService::new(state.db.clone())
+        .refresh(&request.refresh_token, &state.config.auth)
+        .await?;
+
+    let actor = AuditActor {
+        user_id: Some(user.id.to_string()),
+        username: Some(user.username.clone()),
+    };
+
+    Ok((Extension(actor), Json(login_response(user, tokens))))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/logout", tag = "auth",
+    request_body(content = Option<LogoutRequest>, description = "Optional; without it only the access token is revoked"),
+    responses((status = 200, body = Value))
+)]
+async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Option<Json<LogoutRequest>>,
+) -> Result<(Extension<AuditActor>, Json<serde_json::Value>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    let Json(request) = request.unwrap_or_default();
+    let auth_service = AuthService::new(state.db.clone());
+
+    auth_service.revoke_access_token(&claims).await?;
+
+    if request.all {
+        auth_service.revoke_user_tokens(user.id).await?;
+    } else if let Some(refresh_token) = &request.refresh_token {
+        auth_service
+            .revoke_refresh_token(user.id, refresh_token)
+            .await?;
     }
 
-    // If we get here, authentication failed
-    Err(AppError::Auth("Invalid username or password".to_string()))
+    info!("User '{}' logged out", user.username);
+    Ok((
+        Extension(AuditActor::from(&claims)),
+        Json(serde_json::json!({
+            "success": true
+        })),
+    ))
 }
 
-async fn validate_token() -> Result<Json<serde_json::Value>> {
-    // This endpoint is just a placeholder
-    // The actual token validation happens in the middleware
+#[utoipa::path(
+    get, path = "/api/v1/auth/validate", tag = "auth",
+    responses((status = 200, body = Value, description = "`{ valid, user }`"))
+)]
+async fn validate_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>> {
+    // Same checks as the `protect` middleware, so a disabled account is
+    // reported as invalid even if its token has not expired yet
+    let (_claims, user) = authorize(&state, &headers).await?;
+
     Ok(Json(serde_json::json!({
-        "valid": true
+        "valid": true,
+        "user": UserInfo::from(user)
     })))
 }
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/password", tag = "auth",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, body = LoginResponse,
+            description = "New tokens; every earlier session of the user, this one included, is revoked"),
+        (status = 429, body = crate::error::ErrorBody, description = "Too many wrong current passwords",
+            headers(("Retry-After" = i64, description = "Seconds until the next attempt is allowed")))
+    )
+)]
+async fn change_password(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<(Extension<AuditActor>, Json<LoginResponse>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    require_current_password(&state, &user, &remote, &request.current_password).await?;
+
+    PasswordService::new(state.db.clone(), &state.config.auth.password)
+        .change(&user, &request.new_password, Utc::now())
+        .await?;
+    let tokens = AuthService::new(state.db.clone())
+        .issue_tokens(&user, &state.config.auth)
+        .await?;
+    info!("User '{}' changed their password", user.username);
+
+    Ok((
+        Extension(AuditActor::from(&claims)),
+        Json(login_response(user, tokens)),
+    ))
+}
+
+/// Wrong current passwords count as failed logins, so a stolen access token
+/// cannot be used to guess the password.
+async fn require_current_password(
+    state: &AppState,
+    user: &User,
+    remote: &SocketAddr,
+    password: &str,
+) -> Result<()> {
+    let throttle = LoginThrottleService::new(state.db.clone(), &state.config.auth.lockout);
+    let ip = remote.ip().to_string();
+    let now = Utc::now();
+    if let Some(retry_after) = throttle.retry_after(Some(&ip), &user.username, now).await? {
+        return Err(AppError::TooManyAttempts(retry_after));
+    }
+
+    if verify_password(user, password)? {
+        return Ok(());
+    }
+    record_failed_attempt(state, &throttle, &ip, &user.username, now).await?;
+    Err(AppError::InvalidFields(vec![FieldError::new(
+        "current_password",
+        "Current password is incorrect",
+    )]))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/password/reset", tag = "auth", security(()),
+    request_body = ResetPasswordRequest,
+    responses((status = 200, body = Value, description = "`{ success }`; log in with the new password"))
+)]
+async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<(Extension<AuditActor>, Json<serde_json::Value>)> {
+    let user = PasswordService::new(state.db.clone(), &state.config.auth.password)
+        .reset(&request.reset_token, &request.new_password, Utc::now())
+        .await?;
+    info!("User '{}' reset their password", user.username);
+
+    let actor = AuditActor {
+        user_id: Some(user.id.to_string()),
+        username: Some(user.username.clone()),
+    };
+    Ok((
+        Extension(actor),
+        Json(serde_json::json!({
+            "success": true
+        })),
+    ))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/auth/totp", tag = "auth",
+    responses((status = 200, body = TotpStatus))
+)]
+async fn get_totp_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<TotpStatus>> {
+    let (_claims, user) = authorize(&state, &headers).await?;
+    let status = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .status(user.id, &user.role)
+        .await?;
+    Ok(Json(status))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/totp/enroll", tag = "auth",
+    responses((status = 200, body = TotpEnrollment))
+)]
+async fn enroll_totp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<(Extension<AuditActor>, Json<TotpEnrollment>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    let enrollment = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .begin_enrollment(&user)
+        .await?;
+    info!("User '{}' started two-factor enrollment", user.username);
+    Ok((Extension(AuditActor::from(&claims)), Json(enrollment)))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/totp/confirm", tag = "auth",
+    request_body = TotpCodeRequest,
+    responses((status = 200, body = TotpRecoveryCodes))
+)]
+async fn confirm_totp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<TotpCodeRequest>,
+) -> Result<(Extension<AuditActor>, Json<TotpRecoveryCodes>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    let recovery_codes = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .confirm_enrollment(&user, &request.code, Utc::now())
+        .await?;
+    info!("User '{}' enabled two-factor authentication", user.username);
+    Ok((
+        Extension(AuditActor::from(&claims)),
+        Json(TotpRecoveryCodes { recovery_codes }),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/auth/totp/recovery-codes", tag = "auth",
+    request_body = TotpCodeRequest,
+    responses((status = 200, body = TotpRecoveryCodes, description = "New codes; the old ones stop working"))
+)]
+async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<TotpCodeRequest>,
+) -> Result<(Extension<AuditActor>, Json<TotpRecoveryCodes>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    require_totp_code(&state, &user, &remote, &request.code).await?;
+
+    let recovery_codes = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .regenerate_recovery_codes(user.id)
+        .await?;
+    info!("User '{}' regenerated their recovery codes", user.username);
+    Ok((
+        Extension(AuditActor::from(&claims)),
+        Json(TotpRecoveryCodes { recovery_codes }),
+    ))
+}
+
+/// Members of a role that requires 2FA cannot turn it off themselves; an
+/// admin can still reset it for them.
+#[utoipa::path(
+    post, path = "/api/v1/auth/totp/disable", tag = "auth",
+    request_body = TotpCodeRequest,
+    responses((status = 200, body = Value))
+)]
+async fn disable_totp(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<TotpCodeRequest>,
+) -> Result<(Extension<AuditActor>, Json<serde_json::Value>)> {
+    let (claims, user) = authorize(&state, &headers).await?;
+    // Checked before the code, so a refused request does not spend it
+    if PermissionService::new(state.db.clone())
+        .requires_totp(&user.role)
+        .await?
+    {
+        return Err(AppError::TotpEnrollmentRequired);
+    }
+    require_totp_code(&state, &user, &remote, &request.code).await?;
+
+    TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .disable(user.id)
+        .await?;
+    info!(
+        "User '{}' disabled two-factor authentication",
+        user.username
+    );
+    Ok((
+        Extension(AuditActor::from(&claims)),
+        Json(serde_json::json!({
+            "success": true
+        })),
+    ))
+}
+
+/// A stolen access token alone must not be enough to change the second factor.
+async fn require_totp_code(
+    state: &AppState,
+    user: &User,
+    remote: &SocketAddr,
+    code: &str,
+) -> Result<()> {
+    if check_otp(state, user, &remote.ip().to_string(), code, Utc::now()).await? {
+        Ok(())
+    } else {
+        Err(AppError::Validation(
+            "Invalid verification code".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use tracing::info;
+use uuid::Uuid;
+
+use super::{applications, server, sessions, system};
+use crate::{
+    auth::require_permission,
+    error::Result,
+    fleet,
+    models::{CreateDaemon, Daemon, DaemonDb, Permission, UpdateDaemon},
+    services::daemon::DaemonService,
+    AppState,
+};
+
+pub fn create_routes() -> Router<AppState> {
+    let read = Router::new()
+        .route("/", get(list_daemons))
+        .route("/:daemon_id", get(get_daemon))
+        .route_layer(from_fn_with_state(
+            Permission::ServerRead,
+            require_permission,
+        ));
+
+    let manage = Router::new()
+        .route("/", post(create_daemon))
+        .route("/:daemon_id", put(update_daemon).delete(delete_daemon))
+        .route_layer(from_fn_with_state(
+            Permission::DaemonsManage,
+            require_permission,
+        ));
+
+    read.merge(manage)
+}
+
+/// Routes that act on one daemon, nested under `/daemons/:daemon_id`. The
+/// caller layers them with [`fleet::resolve_daemon`].
+pub fn create_scoped_routes() -> Router<AppState> {
+    Router::new()
+        .nest("/server", server::create_routes())
+        .nest("/applications", applications::create_routes())
+        .nest("/sessions", sessions::create_routes())
+        .nest("/system", system::create_daemon_routes())
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons", tag = "daemons",
+    responses((status = 200, body = [Daemon]))
+)]
+async fn list_daemons(State(state): State<AppState>) -> Result<Json<Vec<Daemon>>> {
+    let daemons = DaemonService::new(state.db.clone()).list().await?;
+    Ok(Json(daemons.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}", tag = "daemons",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = Daemon))
+)]
+async fn get_daemon(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<Json<Daemon>> {
+    let daemon = DaemonService::new(state.db.clone()).get(id).await?;
+    Ok(Json(daemon.into()))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/daemons", tag = "daemons",
+    request_body = CreateDaemon,
+    responses((status = 201, body = Daemon))
+)]
+async fn create_daemon(
+    State(state): State<AppState>,
+    Json(request): Json<CreateDaemon>,
+) -> Result<(StatusCode, Json<Daemon>)> {
+    let daemon = DaemonService::new(state.db.clone())
+        .create(&request)
+        .await?;
+    register(&state, &daemon)?;
+    info!("Registered RCP daemon '{}' at {}", daemon.name, daemon.url);
+    Ok((StatusCode::CREATED, Json(daemon.into())))
+}
+
+#[utoipa::path(
+    put, path = "/api/v1/daemons/{daemon_id}", tag = "daemons",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    request_body = UpdateDaemon,
+    responses((status = 200, body = Daemon))
+)]
+async fn update_daemon(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(update): Json<UpdateDaemon>,
+) -> Result<Json<Daemon>> {
+    let daemon = DaemonService::new(state.db.clone())
+        .update(id, &update)
+        .await?;
+    register(&state, &daemon)?;
+    info!("Updated RCP daemon '{}'", daemon.name);
+    Ok(Json(daemon.into()))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/daemons/{daemon_id}", tag = "daemons",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = Value))
+)]
+async fn delete_daemon(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    let service = DaemonService::new(state.db.clone());
+    let daemon = service.get(id).await?;
+    service.delete(id).await?;
+    state.daemons.remove(id);
+    info!("Removed RCP daemon '{}'", daemon.name);
+    Ok(Json(serde_json::json!({
+        "message": format!("Daemon {} removed successfully", daemon.name),
+        "success": true
+    })))
+}
+
+/// Points the registry at the daemon's current settings and checks its
+/// health right away rather than at the next poll.
+fn register(state: &AppState, daemon: &DaemonDb) -> Result<()> {
+    let handle = state.daemons.upsert(daemon)?;
+    if handle.enabled {
+        let state = state.clone();
+        tokio::spawn(async move { fleet::check_health(&state, &handle).await });
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use axum::{extract::State, middleware::from_fn_with_state, response::Json, routing::get, Router};
+use futures_util::future::join_all;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    auth::require_permission,
+    error::Result,
+    models::{FleetDaemonStatus, FleetError, FleetSession, FleetSessions, FleetStatus, Permission},
+    services::daemon::DaemonService,
+    AppState,
+};
+
+/// Views across every enabled daemon. A daemon that does not answer is
+/// reported alongside the others rather than failing the request.
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/status",
+            get(get_fleet_status).route_layer(from_fn_with_state(
+                Permission::ServerRead,
+                require_permission,
+            )),
+        )
+        .route(
+            "/sessions",
+            get(get_fleet_sessions).route_layer(from_fn_with_state(
+                Permission::SessionsRead,
+                require_permission,
+            )),
+        )
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/fleet/status", tag = "fleet",
+    responses((status = 200, body = FleetStatus))
+)]
+async fn get_fleet_status(State(state): State<AppState>) -> Result<Json<FleetStatus>> {
+    let daemons = state.daemons.enabled();
+    let service = DaemonService::new(state.db.clone());
+    let (records, statuses) = tokio::join!(
+        service.list(),
+        join_all(daemons.iter().map(|daemon| daemon.client.get_status()))
+    );
+    let mut records: HashMap<Uuid, _> = records?
+        .into_iter()
+        .filter_map(|record| Some((Uuid::parse_str(&record.id).ok()?, record)))
+        .collect();
+
+    let daemons: Vec<FleetDaemonStatus> = daemons
+        .iter()
+        .zip(statuses)
+        .filter_map(|(daemon, status)| {
+            // Deleted while the status was fetched
+            let record = records.remove(&daemon.id)?;
+            let (status, error) = match status {
+                Ok(status) => (Some(status), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            Some(FleetDaemonStatus {
+                daemon: record.into(),
+                status,
+                error,
+            })
+        })
+        .collect();
+
+    let answered = daemons.iter().filter_map(|d| d.status.as_ref());
+    Ok(Json(FleetStatus {
+        total: daemons.len(),
+        reachable: answered.clone().count(),
+        active_sessions: answered.map(|s| u64::from(s.active_sessions)).sum(),
+        daemons,
+    }))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/fleet/sessions", tag = "fleet",
+    responses((status = 200, body = FleetSessions))
+)]
+async fn get_fleet_sessions(State(state): State<AppState>) -> Result<Json<FleetSessions>> {
+    let daemons = state.daemons.enabled();
+    let results = join_all(daemons.iter().map(|daemon| daemon.client.get_sessions())).await;
+
+    let mut fleet = FleetSessions {
+        sessions: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (daemon, result) in daemons.iter().zip(results) {
+        match result {
+            Ok(sessions) => {
+                fleet
+                    .sessions
+                    .extend(sessions.into_iter().map(|session| FleetSession {
+                        daemon_id: daemon.id,
+                        daemon_name: daemon.name.clone(),
+                        session,
+                    }))
+            }
+            Err(e) => fleet.errors.push(FleetError {
+                daemon_id: daemon.id,
+                daemon_name: daemon.name.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(Json(fleet))
+}
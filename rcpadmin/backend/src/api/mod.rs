@@ -1,23 +1,42 @@
-use axum::Router;
+use axum::{middleware::from_fn_with_state, Router};
 
+pub mod api_keys;
 pub mod applications;
 pub mod auth;
+pub mod daemons;
+pub mod fleet;
+pub mod openapi;
+pub mod roles;
 pub mod server;
 pub mod sessions;
 pub mod system;
 pub mod users;
 
-use crate::AppState;
+use crate::{auth::protect, fleet::resolve_daemon, AppState};
 
-pub fn create_routes() -> Router<AppState> {
-    Router::new()
-        // Auth routes (no authentication required)
-        .nest("/auth", auth::create_routes())
-        // Protected routes (require authentication)
-        .nest("/server", server::create_routes())
-        .nest("/applications", applications::create_routes())
-        .nest("/sessions", sessions::create_routes())
+/// Every route under `/api/v1` with its authentication middleware, as served
+/// by `main`. The OpenAPI drift test sends requests through this router.
+pub fn create_routes(state: &AppState) -> Router<AppState> {
+    // Protected routes (require authentication); each route checks its own
+    // permission
+    let protected = Router::new()
+        .nest("/daemons", daemons::create_routes())
+        .nest(
+            "/daemons/:daemon_id",
+            daemons::create_scoped_routes()
+                .route_layer(from_fn_with_state(state.clone(), resolve_daemon)),
+        )
+        .nest("/fleet", fleet::create_routes())
         .nest("/system", system::create_routes())
-        // Admin-only routes
         .nest("/users", users::create_routes())
+        .nest("/roles", roles::create_routes())
+        .nest("/api-keys", api_keys::create_routes())
+        .layer(from_fn_with_state(state.clone(), protect));
+
+    Router::new()
+        // Auth routes (no middleware)
+        .nest("/auth", auth::create_routes())
+        // API description for client generators (no middleware)
+        .merge(openapi::create_routes())
+        .merge(protected)
 }
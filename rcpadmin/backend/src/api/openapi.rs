@@ -0,0 +1,417 @@
+use axum::{response::Json, routing::get, Router};
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme},
+        Ref, RefOr,
+    },
+    Modify, OpenApi,
+};
+
+use super::{api_keys, applications, auth, daemons, fleet, roles, server, sessions, system, users};
+use crate::{error, models, AppState};
+
+/// OpenAPI 3 description of `/api/v1`, built from the `#[utoipa::path]`
+/// attributes on the handlers. New routes go in `paths` too; a test checks
+/// this list and the routes [`crate::api::create_routes`] serves against the
+/// route table in its module.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "RCP Admin API"),
+    paths(
+        auth::login,
+        auth::refresh,
+        auth::logout,
+        auth::validate_token,
+        auth::change_password,
+        auth::reset_password,
+        auth::verify_otp,
+        auth::get_totp_status,
+        auth::enroll_totp,
+        auth::confirm_totp,
+        auth::regenerate_recovery_codes,
+        auth::disable_totp,
+        daemons::list_daemons,
+        daemons::get_daemon,
+        daemons::create_daemon,
+        daemons::update_daemon,
+        daemons::delete_daemon,
+        fleet::get_fleet_status,
+        fleet::get_fleet_sessions,
+        server::get_status,
+        server::restart_server,
+        server::get_config,
+        applications::get_applications,
+        applications::get_application,
+        applications::create_application,
+        applications::update_application,
+        applications::delete_application,
+        applications::launch_application,
+        applications::stop_application,
+        applications::get_launch_history,
+        sessions::get_sessions,
+        sessions::get_session,
+        sessions::get_session_metrics,
+        sessions::close_session,
+        system::get_metrics,
+        system::health_check,
+        system::get_logs,
+        system::get_audit_events,
+        system::export_audit_events,
+        users::list_users,
+        users::get_user,
+        users::create_user,
+        users::update_user,
+        users::delete_user,
+        users::unlock_user,
+        users::create_password_reset,
+        users::reset_totp,
+        roles::list_roles,
+        roles::update_role,
+        api_keys::list_api_keys,
+        api_keys::create_api_key,
+        api_keys::revoke_api_key,
+    ),
+    components(
+        schemas(
+            error::ErrorBody,
+            error::ErrorCode,
+            error::FieldError,
+            models::UserInfo,
+            models::CreateUser,
+            models::UpdateUser,
+            models::UserList,
+            models::UserRole,
+            models::LoginRequest,
+            models::LoginResponse,
+            models::LoginOutcome,
+            models::OtpChallenge,
+            models::VerifyOtpRequest,
+            models::TotpEnrollment,
+            models::TotpCodeRequest,
+            models::TotpRecoveryCodes,
+            models::TotpStatus,
+            models::RefreshRequest,
+            models::LogoutRequest,
+            models::ChangePasswordRequest,
+            models::ResetPasswordRequest,
+            models::PasswordResetToken,
+            models::Permission,
+            models::RolePermissions,
+            models::UpdateRolePermissions,
+            models::AuditEvent,
+            models::AuditEventList,
+            models::Daemon,
+            models::DaemonHealth,
+            models::CreateDaemon,
+            models::UpdateDaemon,
+            models::FleetStatus,
+            models::FleetHealth,
+            models::SystemHealth,
+            models::FleetDaemonStatus,
+            models::FleetSession,
+            models::FleetSessions,
+            models::FleetError,
+            models::ServerStatus,
+            models::SystemMetrics,
+            models::Session,
+            models::SessionStatus,
+            models::SessionMetrics,
+            models::SystemMetricsSeries,
+            models::SessionMetricsSeries,
+            models::SystemMetricsPoint,
+            models::SessionMetricsPoint,
+            models::Application,
+            models::CreateApplication,
+            models::SavedApplication,
+            models::LaunchStatus,
+            models::LaunchRecord,
+            models::LaunchApplication,
+            models::LaunchHistory,
+            models::LogLevel,
+            models::LogSource,
+            models::LogEntry,
+            models::LogList,
+            models::ApiKey,
+            models::CreateApiKey,
+            models::CreatedApiKey,
+        ),
+        responses(error::ErrorBody)
+    ),
+    modifiers(&SecuritySchemes, &ErrorResponses),
+    security(("bearer_auth" = []), ("api_key" = [])),
+    tags(
+        (name = "auth", description = "Login, tokens and two-factor authentication"),
+        (name = "daemons", description = "The registered RCP daemons"),
+        (name = "fleet", description = "Status and sessions across all daemons"),
+        (name = "server", description = "RCP daemon status and control"),
+        (name = "applications", description = "Applications and their launches"),
+        (name = "sessions", description = "Live daemon sessions"),
+        (name = "system", description = "Metrics, logs and the audit trail"),
+        (name = "users", description = "User accounts"),
+        (name = "roles", description = "Role permissions"),
+        (name = "api-keys", description = "Keys for automation"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+        // `Authorization: ApiKey <key>`; OpenAPI cannot express the prefix
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Authorization",
+                "`ApiKey <key>`, with a key from /api/v1/api-keys",
+            ))),
+        );
+    }
+}
+
+/// Every route can fail with the standard error body, so it is declared
+/// once as each operation's `default` response instead of per handler.
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            for operation in item.operations.values_mut() {
+                operation.responses.responses.insert(
+                    "default".to_string(),
+                    RefOr::Ref(Ref::from_response_name("ErrorBody")),
+                );
+            }
+        }
+    }
+}
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new().route("/openapi.json", get(openapi_json))
+}
+
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use chrono::Duration;
+    use std::collections::BTreeSet;
+    use tower::ServiceExt;
+    use utoipa::openapi::PathItemType;
+    use uuid::Uuid;
+
+    use crate::services::auth::AuthService;
+
+    /// Every route `api::create_routes` serves. A new route goes here and in
+    /// `ApiDoc`; the tests check both against each other and the router.
+    const ROUTES: &[(&str, &str)] = &[
+        ("POST", "/api/v1/auth/login"),
+        ("POST", "/api/v1/auth/refresh"),
+        ("POST", "/api/v1/auth/logout"),
+        ("GET", "/api/v1/auth/validate"),
+        ("POST", "/api/v1/auth/password"),
+        ("POST", "/api/v1/auth/password/reset"),
+        ("POST", "/api/v1/auth/verify-otp"),
+        ("GET", "/api/v1/auth/totp"),
+        ("POST", "/api/v1/auth/totp/enroll"),
+        ("POST", "/api/v1/auth/totp/confirm"),
+        ("POST", "/api/v1/auth/totp/recovery-codes"),
+        ("POST", "/api/v1/auth/totp/disable"),
+        ("GET", "/api/v1/daemons"),
+        ("POST", "/api/v1/daemons"),
+        ("GET", "/api/v1/daemons/{daemon_id}"),
+        ("PUT", "/api/v1/daemons/{daemon_id}"),
+        ("DELETE", "/api/v1/daemons/{daemon_id}"),
+        ("GET", "/api/v1/daemons/{daemon_id}/server/status"),
+        ("POST", "/api/v1/daemons/{daemon_id}/server/restart"),
+        ("GET", "/api/v1/daemons/{daemon_id}/server/config"),
+        ("GET", "/api/v1/daemons/{daemon_id}/applications"),
+        ("POST", "/api/v1/daemons/{daemon_id}/applications"),
+        ("GET", "/api/v1/daemons/{daemon_id}/applications/{id}"),
+        ("PUT", "/api/v1/daemons/{daemon_id}/applications/{id}"),
+        ("DELETE", "/api/v1/daemons/{daemon_id}/applications/{id}"),
+        (
+            "GET",
+            "/api/v1/daemons/{daemon_id}/applications/{id}/launches",
+        ),
+        (
+            "POST",
+            "/api/v1/daemons/{daemon_id}/applications/{id}/launch",
+        ),
+        ("POST", "/api/v1/daemons/{daemon_id}/applications/{id}/stop"),
+        ("GET", "/api/v1/daemons/{daemon_id}/sessions"),
+        ("GET", "/api/v1/daemons/{daemon_id}/sessions/{id}"),
+        ("DELETE", "/api/v1/daemons/{daemon_id}/sessions/{id}"),
+        ("GET", "/api/v1/daemons/{daemon_id}/sessions/{id}/metrics"),
+        ("GET", "/api/v1/daemons/{daemon_id}/system/metrics"),
+        ("GET", "/api/v1/fleet/status"),
+        ("GET", "/api/v1/fleet/sessions"),
+        ("GET", "/api/v1/system/health"),
+        ("GET", "/api/v1/system/audit"),
+        ("GET", "/api/v1/system/audit/export"),
+        ("GET", "/api/v1/system/logs"),
+        ("GET", "/api/v1/users"),
+        ("POST", "/api/v1/users"),
+        ("GET", "/api/v1/users/{id}"),
+        ("PUT", "/api/v1/users/{id}"),
+        ("DELETE", "/api/v1/users/{id}"),
+        ("POST", "/api/v1/users/{id}/unlock"),
+        ("POST", "/api/v1/users/{id}/password-reset"),
+        ("DELETE", "/api/v1/users/{id}/totp"),
+        ("GET", "/api/v1/roles"),
+        ("PUT", "/api/v1/roles/{role}"),
+        ("GET", "/api/v1/api-keys"),
+        ("POST", "/api/v1/api-keys"),
+        ("DELETE", "/api/v1/api-keys/{id}"),
+    ];
+
+    /// Id of the seeded admin user and default daemon
+    const SEEDED_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::DELETE,
+        Method::PATCH,
+    ];
+
+    fn listed() -> BTreeSet<(String, String)> {
+        ROUTES
+            .iter()
+            .map(|(method, path)| (method.to_string(), path.to_string()))
+            .collect()
+    }
+
+    fn documented() -> BTreeSet<(String, String)> {
+        ApiDoc::openapi()
+            .paths
+            .paths
+            .into_iter()
+            .flat_map(|(path, item)| {
+                item.operations.into_keys().map(move |method| {
+                    let method = match method {
+                        PathItemType::Get => "GET",
+                        PathItemType::Post => "POST",
+                        PathItemType::Put => "PUT",
+                        PathItemType::Delete => "DELETE",
+                        PathItemType::Patch => "PATCH",
+                        PathItemType::Head => "HEAD",
+                        PathItemType::Options => "OPTIONS",
+                        PathItemType::Trace => "TRACE",
+                        PathItemType::Connect => "CONNECT",
+                    };
+                    (method.to_string(), path.clone())
+                })
+            })
+            .collect()
+    }
+
+    /// Status of `method path` sent by the admin through the served router,
+    /// with `{daemon_id}` set to `daemon` and the other `{param}`s filled in
+    /// by random ids. Paths the router does not know answer 418.
+    async fn send(state: &AppState, method: Method, path: &str, daemon: Uuid) -> StatusCode {
+        let router = Router::new()
+            .nest("/api/v1", crate::api::create_routes(state))
+            .fallback(|| async { StatusCode::IM_A_TEAPOT })
+            .with_state(state.clone());
+
+        let uri = path
+            .split('/')
+            .map(|segment| match segment {
+                "{daemon_id}" => daemon.to_string(),
+                _ if segment.starts_with('{') => Uuid::new_v4().to_string(),
+                _ => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        // A fresh token for each request, as logout revokes the one it gets
+        let admin = AuthService::new(state.db.clone())
+            .get_user_by_id(SEEDED_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let token = crate::auth::generate_token(
+            &admin,
+            &state.config.auth.jwt_secret,
+            Duration::minutes(5),
+        )
+        .unwrap();
+
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let listed = listed();
+        let documented = documented();
+
+        let undocumented: Vec<_> = listed.difference(&documented).collect();
+        assert!(
+            undocumented.is_empty(),
+            "routes missing from ApiDoc: {:?}",
+            undocumented
+        );
+
+        let unlisted: Vec<_> = documented.difference(&listed).collect();
+        assert!(
+            unlisted.is_empty(),
+            "ApiDoc paths missing from ROUTES: {:?}",
+            unlisted
+        );
+    }
+
+    #[tokio::test]
+    async fn the_router_serves_the_listed_routes() {
+        let state = crate::test_state().await;
+        let listed = listed();
+        let paths: BTreeSet<_> = ROUTES.iter().map(|(_, path)| *path).collect();
+
+        for path in paths {
+            for method in METHODS {
+                if listed.contains(&(method.to_string(), path.to_string())) {
+                    // Random ids keep the handlers from changing anything;
+                    // a missing daemon is still a routed request
+                    let status = send(&state, method.clone(), path, Uuid::new_v4()).await;
+                    assert!(
+                        status != StatusCode::IM_A_TEAPOT
+                            && status != StatusCode::METHOD_NOT_ALLOWED,
+                        "{} {} is not routed ({})",
+                        method,
+                        path,
+                        status
+                    );
+                } else {
+                    // Daemon routes only answer 405 once the daemon is found
+                    let daemon = SEEDED_ID.parse().unwrap();
+                    let status = send(&state, method.clone(), path, daemon).await;
+                    assert_eq!(
+                        status,
+                        StatusCode::METHOD_NOT_ALLOWED,
+                        "{} {} is routed but not listed",
+                        method,
+                        path
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use axum::{
+    extract::{Path, State},
+    middleware::from_fn_with_state,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use tracing::info;
+
+use crate::{
+    auth::require_permission,
+    error::Result,
+    models::{Permission, RolePermissions, UpdateRolePermissions, UserRole},
+    services::permission::PermissionService,
+    AppState,
+};
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_roles))
+        .route("/:role", put(update_role))
+        .route_layer(from_fn_with_state(
+            Permission::RolesManage,
+            require_permission,
+        ))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/roles", tag = "roles",
+    responses((status = 200, body = [RolePermissions]))
+)]
+async fn list_roles(State(state): State<AppState>) -> Result<Json<Vec<RolePermissions>>> {
+    let roles = PermissionService::new(state.db.clone())
+        .list_roles()
+        .await?;
+    Ok(Json(roles))
+}
+
+#[utoipa::path(
+    put, path = "/api/v1/roles/{role}", tag = "roles",
+    params(("role" = UserRole, Path, description = "Role name")),
+    request_body = UpdateRolePermissions,
+    responses((status = 200, body = RolePermissions))
+)]
+async fn update_role(
+    State(state): State<AppState>,
+    Path(role): Path<UserRole>,
+    Json(update): Json<UpdateRolePermissions>,
+) -> Result<Json<RolePermissions>> {
+    let service = PermissionService::new(state.db.clone());
+    service
+        .set_role_permissions(&role, &update.permissions)
+        .await?;
+    if let Some(require_totp) = update.require_totp {
+        service.set_requires_totp(&role, require_totp).await?;
+    }
+
+    let mut permissions: Vec<Permission> =
+        service.role_permissions(&role).await?.into_iter().collect();
+    permissions.sort_by_key(|p| p.as_str());
+    let require_totp = service.requires_totp(&role).await?;
+    info!("Updated permissions of role {}", role);
+
+    Ok(Json(RolePermissions {
+        role,
+        permissions,
+        require_totp,
+    }))
+}
@@ -1,37 +1,73 @@
-use axum::{extract::State, response::Json, routing::get, Router};
+use axum::{
+    middleware::from_fn_with_state,
+    response::Json,
+    routing::{get, post},
+    Extension, Router,
+};
 use serde_json::Value;
+use std::sync::Arc;
+use tracing::info;
 
-use crate::{error::Result, AppState};
+use crate::{
+    auth::require_permission,
+    error::Result,
+    fleet::DaemonHandle,
+    models::{Permission, ServerStatus},
+    AppState,
+};
 
 pub fn create_routes() -> Router<AppState> {
     Router::new()
-        .route("/status", get(get_status))
-        .route("/restart", get(restart_server))
-        .route("/config", get(get_config))
+        .route(
+            "/status",
+            get(get_status).route_layer(from_fn_with_state(
+                Permission::ServerRead,
+                require_permission,
+            )),
+        )
+        .route(
+            "/restart",
+            post(restart_server).route_layer(from_fn_with_state(
+                Permission::ServerRestart,
+                require_permission,
+            )),
+        )
+        .route(
+            "/config",
+            get(get_config).route_layer(from_fn_with_state(
+                Permission::ServerConfig,
+                require_permission,
+            )),
+        )
 }
 
-async fn get_status(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "status": "running",
-        "uptime": "0h 0m 0s",
-        "version": "unknown",
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/server/status", tag = "server",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = ServerStatus))
+)]
+async fn get_status(Extension(daemon): Extension<Arc<DaemonHandle>>) -> Result<Json<ServerStatus>> {
+    let status = daemon.client.get_status().await?;
+    Ok(Json(status))
 }
 
-async fn restart_server(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "success": false,
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    post, path = "/api/v1/daemons/{daemon_id}/server/restart", tag = "server",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = Value))
+)]
+async fn restart_server(Extension(daemon): Extension<Arc<DaemonHandle>>) -> Result<Json<Value>> {
+    let result = daemon.client.restart_server().await?;
+    info!("Requested restart of RCP daemon '{}'", daemon.name);
+    Ok(Json(result))
 }
 
-async fn get_config(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "config": {},
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/server/config", tag = "server",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = Value, description = "The daemon configuration as it reports it"))
+)]
+async fn get_config(Extension(daemon): Extension<Arc<DaemonHandle>>) -> Result<Json<Value>> {
+    let config = daemon.client.get_config().await?;
+    Ok(Json(config))
 }
@@ -1,27 +1,104 @@
-use axum::{extract::State, response::Json, routing::get, Router};
-use serde_json::Value;
+use axum::{
+    extract::{Path, Query, State},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get},
+    Extension, Router,
+};
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
 
-use crate::{error::Result, AppState};
+use crate::{
+    auth::require_permission,
+    error::Result,
+    fleet::DaemonHandle,
+    models::{MetricsRangeQuery, Permission, Session},
+    services::metrics_history::MetricsHistoryService,
+    AppState,
+};
 
 pub fn create_routes() -> Router<AppState> {
-    Router::new()
+    let read = Router::new()
         .route("/", get(get_sessions))
         .route("/:id", get(get_session))
-        // .route("/:id", delete(close_session))
+        .route("/:id/metrics", get(get_session_metrics))
+        .route_layer(from_fn_with_state(
+            Permission::SessionsRead,
+            require_permission,
+        ));
+
+    let terminate = Router::new()
+        .route("/:id", delete(close_session))
+        .route_layer(from_fn_with_state(
+            Permission::SessionsTerminate,
+            require_permission,
+        ));
+
+    read.merge(terminate)
 }
 
-async fn get_sessions(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "sessions": [],
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/sessions", tag = "sessions",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id")),
+    responses((status = 200, body = [Session]))
+)]
+async fn get_sessions(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+) -> Result<Json<Vec<Session>>> {
+    let sessions = daemon.client.get_sessions().await?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/sessions/{id}", tag = "sessions",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = Uuid, Path, description = "Session id")),
+    responses((status = 200, body = Session))
+)]
+async fn get_session(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Session>> {
+    let session = daemon.client.get_session(id).await?;
+    Ok(Json(session))
+}
+
+/// Current metrics from the daemon, or a recorded series when a range is given.
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/sessions/{id}/metrics", tag = "sessions",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = Uuid, Path, description = "Session id"), MetricsRangeQuery),
+    responses((status = 200, body = SessionMetrics, description = "Current metrics, or a `SessionMetricsSeries` when a range is given"))
+)]
+async fn get_session_metrics(
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, Uuid)>,
+    Query(range): Query<MetricsRangeQuery>,
+) -> Result<Response> {
+    if range.is_empty() {
+        let metrics = daemon.client.get_session_metrics(id).await?;
+        return Ok(Json(metrics).into_response());
+    }
+
+    let series = MetricsHistoryService::new(state.db.clone())
+        .session_series(daemon.id, id, &range)
+        .await?;
+    Ok(Json(series).into_response())
 }
 
-async fn get_session(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
+#[utoipa::path(
+    delete, path = "/api/v1/daemons/{daemon_id}/sessions/{id}", tag = "sessions",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), ("id" = Uuid, Path, description = "Session id")),
+    responses((status = 200, body = Value))
+)]
+async fn close_session(
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Path((_, id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    daemon.client.close_session(id).await?;
+    info!("Closed session {} on RCP daemon '{}'", id, daemon.name);
     Ok(Json(serde_json::json!({
-        "session": null,
-        "message": "RCP daemon not connected - mock response"
+        "message": format!("Session {} closed successfully", id),
+        "success": true
     })))
 }
@@ -1,38 +1,245 @@
-use axum::{extract::State, response::Json, routing::get, Router};
-use serde_json::Value;
+use axum::{
+    extract::{Query, State},
+    http::header,
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Extension, Router,
+};
+use chrono::Utc;
+use std::sync::Arc;
 
-use crate::{error::Result, AppState};
+use crate::{
+    auth::require_permission,
+    error::{AppError, Result},
+    fleet::DaemonHandle,
+    models::{
+        AuditEvent, AuditEventList, AuditQuery, DaemonHealth, FleetHealth, LogList, LogQuery,
+        MetricsRangeQuery, Permission, SystemHealth,
+    },
+    services::{
+        audit::AuditService, daemon::DaemonService, metrics_history::MetricsHistoryService,
+    },
+    AppState,
+};
 
 pub fn create_routes() -> Router<AppState> {
+    // Logs hold failed-login usernames, client addresses and lockouts, so
+    // they are as sensitive as the audit trail
+    let audit = Router::new()
+        .route("/audit", get(get_audit_events))
+        .route("/audit/export", get(export_audit_events))
+        .route("/logs", get(get_logs))
+        .route_layer(from_fn_with_state(
+            Permission::AuditRead,
+            require_permission,
+        ));
+
     Router::new()
-        .route("/metrics", get(get_metrics))
         .route("/health", get(health_check))
-        .route("/logs", get(get_logs))
+        .route_layer(from_fn_with_state(
+            Permission::SystemRead,
+            require_permission,
+        ))
+        .merge(audit)
+}
+
+/// Routes about one daemon's host, nested under `/daemons/:daemon_id/system`.
+pub fn create_daemon_routes() -> Router<AppState> {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route_layer(from_fn_with_state(
+            Permission::SystemRead,
+            require_permission,
+        ))
+}
+
+/// Current metrics from the daemon, or a recorded series when a range is given.
+#[utoipa::path(
+    get, path = "/api/v1/daemons/{daemon_id}/system/metrics", tag = "system",
+    params(("daemon_id" = Uuid, Path, description = "Daemon id"), MetricsRangeQuery),
+    responses((status = 200, body = SystemMetrics, description = "Current metrics, or a `SystemMetricsSeries` when a range is given"))
+)]
+async fn get_metrics(
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Query(range): Query<MetricsRangeQuery>,
+) -> Result<Response> {
+    if range.is_empty() {
+        let metrics = daemon.client.get_system_metrics().await?;
+        return Ok(Json(metrics).into_response());
+    }
+
+    let series = MetricsHistoryService::new(state.db.clone())
+        .system_series(daemon.id, &range)
+        .await?;
+    Ok(Json(series).into_response())
+}
+
+/// The backend's own state plus the fleet health recorded by the poller.
+#[utoipa::path(
+    get, path = "/api/v1/system/health", tag = "system",
+    responses((status = 200, body = SystemHealth))
+)]
+async fn health_check(State(state): State<AppState>) -> Result<Json<SystemHealth>> {
+    let daemons = DaemonService::new(state.db.clone()).list().await?;
+
+    let mut fleet = FleetHealth::default();
+    for daemon in daemons.iter().filter(|d| d.enabled) {
+        fleet.total += 1;
+        match daemon.health.parse().unwrap_or(DaemonHealth::Unknown) {
+            DaemonHealth::Healthy => fleet.healthy += 1,
+            DaemonHealth::Unreachable => fleet.unreachable += 1,
+            DaemonHealth::Error => fleet.error += 1,
+            DaemonHealth::Unknown => fleet.unknown += 1,
+        }
+    }
+
+    Ok(Json(SystemHealth {
+        status: if fleet.healthy == fleet.total {
+            "healthy"
+        } else {
+            "degraded"
+        }
+        .to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: Utc::now(),
+        daemons: fleet,
+    }))
 }
 
-async fn get_metrics(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "cpu_usage": 0.0,
-        "memory_usage": 0.0,
-        "disk_usage": 0.0,
-        "network_io": {"rx": 0, "tx": 0},
-        "message": "RCP daemon not connected - mock response"
-    })))
+/// Recent backend and daemon log entries; `/ws/logs` tails them live.
+#[utoipa::path(
+    get, path = "/api/v1/system/logs", tag = "system",
+    params(LogQuery),
+    responses((status = 200, body = LogList))
+)]
+async fn get_logs(
+    State(state): State<AppState>,
+    Query(query): Query<LogQuery>,
+) -> Result<Json<LogList>> {
+    Ok(Json(state.logs.query(&query)))
 }
 
-async fn health_check(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "status": "healthy",
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    get, path = "/api/v1/system/audit", tag = "system",
+    params(AuditQuery),
+    responses((status = 200, body = AuditEventList))
+)]
+async fn get_audit_events(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditEventList>> {
+    let events = AuditService::new(state.db.clone()).list(&query).await?;
+    Ok(Json(events))
 }
 
-async fn get_logs(State(_state): State<AppState>) -> Result<Json<Value>> {
-    // Mock response for now - replace with actual rcpdaemon call later
-    Ok(Json(serde_json::json!({
-        "logs": [],
-        "message": "RCP daemon not connected - mock response"
-    })))
+#[utoipa::path(
+    get, path = "/api/v1/system/audit/export", tag = "system",
+    params(AuditQuery),
+    responses((status = 200, description = "Every matching event, as a download",
+        content(("application/json" = Vec<AuditEvent>), ("text/csv" = String))))
+)]
+async fn export_audit_events(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response> {
+    let format = query.format.as_deref().unwrap_or("json");
+    if format != "json" && format != "csv" {
+        return Err(AppError::Validation(format!(
+            "Unsupported export format '{}', expected 'json' or 'csv'",
+            format
+        )));
+    }
+
+    let events = AuditService::new(state.db.clone()).export(&query).await?;
+
+    let response = if format == "csv" {
+        (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"audit.csv\"",
+                ),
+            ],
+            audit_csv(&events),
+        )
+            .into_response()
+    } else {
+        (
+            [(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"audit.json\"",
+            )],
+            Json(events),
+        )
+            .into_response()
+    };
+
+    Ok(response)
+}
+
+fn audit_csv(events: &[AuditEvent]) -> String {
+    let mut csv = String::from(
+        "id,occurred_at,user_id,username,action,resource,outcome,status_code,source_ip\n",
+    );
+
+    for event in events {
+        let fields = [
+            event.id.to_string(),
+            event.occurred_at.to_rfc3339(),
+            event.user_id.clone().unwrap_or_default(),
+            event.username.clone().unwrap_or_default(),
+            event.action.clone(),
+            event.resource.clone(),
+            event.outcome.clone(),
+            event.status_code.to_string(),
+            event.source_ip.clone().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quotes a CSV field when needed, and defuses values a spreadsheet would
+/// evaluate as a formula (usernames and paths are attacker-controlled).
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::csv_field;
+
+    #[test]
+    fn csv_field_defuses_formula_prefixes() {
+        for value in ["=1+1", "+1", "-1", "@SUM(A1)", "\t=1", "\r=1"] {
+            assert!(
+                csv_field(value).trim_start_matches('"').starts_with('\''),
+                "{value:?} was not defused"
+            );
+        }
+    }
+
+    #[test]
+    fn csv_field_quotes_separators() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("\r=1"), "\"'\r=1\"");
+    }
 }
@@ -1,15 +1,29 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
     response::Json,
-    routing::{delete, get, post, put},
-    Router,
+    routing::{delete, get, post},
+    Extension, Router,
 };
+use chrono::Utc;
 use tracing::info;
 use uuid::Uuid;
 
 use crate::{
-    error::Result,
-    models::{CreateUser, UpdateUser, UserInfo, UserRole},
+    auth::{require_permission, Claims},
+    error::{AppError, Result},
+    models::{
+        CreateUser, PasswordResetToken, Permission, UpdateUser, UserInfo, UserList, UserListQuery,
+        UserRole,
+    },
+    services::{
+        auth::AuthService,
+        login_throttle::LoginThrottleService,
+        password::{check_policy, PasswordService},
+        totp::TotpService,
+        user::UserService,
+    },
     AppState,
 };
 
@@ -17,66 +31,207 @@ pub fn create_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(list_users).post(create_user))
         .route("/:id", get(get_user).put(update_user).delete(delete_user))
+        .route("/:id/unlock", post(unlock_user))
+        .route("/:id/password-reset", post(create_password_reset))
+        .route("/:id/totp", delete(reset_totp))
+        .route_layer(from_fn_with_state(
+            Permission::UsersManage,
+            require_permission,
+        ))
 }
 
-async fn list_users(_state: State<AppState>) -> Result<Json<Vec<UserInfo>>> {
-    // Mock implementation - in real app, use state.db to query users
-    let users = vec![UserInfo {
-        id: Uuid::new_v4(),
-        username: "admin".to_string(),
-        email: "admin@example.com".to_string(),
-        role: UserRole::Admin,
-        is_active: true,
-    }];
-    info!("Retrieved {} users", users.len());
+#[utoipa::path(
+    get, path = "/api/v1/users", tag = "users",
+    params(UserListQuery),
+    responses((status = 200, body = UserList))
+)]
+async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<UserListQuery>,
+) -> Result<Json<UserList>> {
+    let users = UserService::new(state.db.clone()).get_users(&query).await?;
+    info!("Retrieved {} of {} users", users.users.len(), users.total);
     Ok(Json(users))
 }
 
-async fn get_user(Path(_user_id): Path<Uuid>) -> Result<Json<UserInfo>> {
-    // Mock implementation
-    let user = UserInfo {
-        id: Uuid::new_v4(),
-        username: "admin".to_string(),
-        email: "admin@example.com".to_string(),
-        role: UserRole::Admin,
-        is_active: true,
-    };
-    Ok(Json(user))
+#[utoipa::path(
+    get, path = "/api/v1/users/{id}", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = UserInfo))
+)]
+async fn get_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserInfo>> {
+    let user = UserService::new(state.db.clone()).get_user(user_id).await?;
+    Ok(Json(user.into()))
 }
 
-async fn create_user(Json(_create_user): Json<CreateUser>) -> Result<Json<UserInfo>> {
-    // Mock implementation
-    let user = UserInfo {
-        id: Uuid::new_v4(),
-        username: "newuser".to_string(),
-        email: "newuser@example.com".to_string(),
-        role: UserRole::Viewer,
-        is_active: true,
-    };
+#[utoipa::path(
+    post, path = "/api/v1/users", tag = "users",
+    request_body = CreateUser,
+    responses((status = 201, body = UserInfo))
+)]
+async fn create_user(
+    State(state): State<AppState>,
+    Json(create_user): Json<CreateUser>,
+) -> Result<(StatusCode, Json<UserInfo>)> {
+    validate_username(&create_user.username)?;
+    check_policy(
+        &state.config.auth.password,
+        "password",
+        &create_user.password,
+        &create_user.username,
+    )?;
+
+    let user = UserService::new(state.db.clone())
+        .create_user(create_user)
+        .await?;
     info!("Created new user: {}", user.username);
-    Ok(Json(user))
+    Ok((StatusCode::CREATED, Json(user.into())))
 }
 
+#[utoipa::path(
+    put, path = "/api/v1/users/{id}", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    request_body = UpdateUser,
+    responses((status = 200, body = UserInfo))
+)]
 async fn update_user(
-    Path(_user_id): Path<Uuid>,
-    Json(_update): Json<UpdateUser>,
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<Uuid>,
+    Json(update): Json<UpdateUser>,
 ) -> Result<Json<UserInfo>> {
-    // Mock implementation
-    let user = UserInfo {
-        id: Uuid::new_v4(),
-        username: "updateduser".to_string(),
-        email: "updated@example.com".to_string(),
-        role: UserRole::Operator,
-        is_active: true,
-    };
+    if let Some(username) = &update.username {
+        validate_username(username)?;
+    }
+
+    // Stop an admin from accidentally locking themselves out
+    if claims.sub == user_id.to_string()
+        && (update.is_active == Some(false)
+            || update.role.as_ref().is_some_and(|r| *r != UserRole::Admin))
+    {
+        return Err(AppError::Validation(
+            "You cannot deactivate or demote your own account".to_string(),
+        ));
+    }
+
+    let user = UserService::new(state.db.clone())
+        .update_user(user_id, update)
+        .await?;
+
+    // Access tokens are already refused for inactive users; also make sure
+    // no refresh token can mint new ones once the account is re-enabled
+    if !user.is_active {
+        AuthService::new(state.db.clone())
+            .revoke_user_tokens(user.id)
+            .await?;
+    }
     info!("Updated user: {}", user.username);
-    Ok(Json(user))
+    Ok(Json(user.into()))
 }
 
-async fn delete_user(Path(user_id): Path<Uuid>) -> Result<Json<serde_json::Value>> {
+#[utoipa::path(
+    delete, path = "/api/v1/users/{id}", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = Value))
+)]
+async fn delete_user(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    if claims.sub == user_id.to_string() {
+        return Err(AppError::Validation(
+            "You cannot delete your own account".to_string(),
+        ));
+    }
+
+    UserService::new(state.db.clone())
+        .delete_user(user_id)
+        .await?;
     info!("Deleted user: {}", user_id);
     Ok(Json(serde_json::json!({
         "message": format!("User {} deleted successfully", user_id),
         "success": true
     })))
 }
+
+#[utoipa::path(
+    post, path = "/api/v1/users/{id}/unlock", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = Value, description = "`{ success, cleared }`; `cleared` is false if there were no failed logins to clear"))
+)]
+async fn unlock_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    let user = UserService::new(state.db.clone()).get_user(user_id).await?;
+
+    // Only the username's counter; the client IPs involved stay throttled
+    let cleared = LoginThrottleService::new(state.db.clone(), &state.config.auth.lockout)
+        .unlock_username(&user.username)
+        .await?;
+    info!("Cleared failed logins for user: {}", user.username);
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "cleared": cleared
+    })))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/users/{id}/password-reset", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = PasswordResetToken,
+        description = "A token for `POST /api/v1/auth/password/reset`; any earlier one stops working"))
+)]
+async fn create_password_reset(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<PasswordResetToken>> {
+    let user = UserService::new(state.db.clone()).get_user(user_id).await?;
+
+    // The current password keeps working until the token is used
+    let token = PasswordService::new(state.db.clone(), &state.config.auth.password)
+        .create_reset_token(user.id, Utc::now())
+        .await?;
+    info!("Issued a password reset token for user: {}", user.username);
+    Ok(Json(token))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/users/{id}/totp", tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = Value, description = "`{ success, was_enabled }`"))
+)]
+async fn reset_totp(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    let user = UserService::new(state.db.clone()).get_user(user_id).await?;
+
+    // For a lost authenticator; the user enrolls again at next login
+    let was_enabled = TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .disable(user.id)
+        .await?;
+    info!("Reset two-factor authentication of user: {}", user.username);
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "was_enabled": was_enabled
+    })))
+}
+
+fn validate_username(username: &str) -> Result<()> {
+    // Same rule the login endpoint applies, so every stored user can sign in
+    if username.is_empty()
+        || !username
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(AppError::Validation(
+            "Username must be non-empty and contain only letters, digits, '_' or '-'".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex};
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::Claims,
+    services::audit::{AuditService, NewAuditEvent},
+    AppState,
+};
+
+/// How often expired audit events are deleted
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Who performed a request, attached to the response extensions so that
+/// [`record_requests`] can attribute the event. Set by the `protect`
+/// middleware for authenticated routes and by the auth handlers for logins.
+#[derive(Debug, Clone, Default)]
+pub struct AuditActor {
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+}
+
+impl From<&Claims> for AuditActor {
+    fn from(claims: &Claims) -> Self {
+        Self {
+            user_id: Some(claims.sub.clone()),
+            username: Some(claims.username.clone()),
+        }
+    }
+}
+
+/// Marks responses to requests that matched a route, so that
+/// [`record_requests`] can tell them from probes of unknown paths.
+#[derive(Debug, Clone, Copy)]
+pub struct Routed;
+
+/// Route layer inserting [`Routed`] into the response extensions.
+pub async fn mark_routed(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(Routed);
+    response
+}
+
+/// Caps the events recorded per client IP for requests without a known user,
+/// so that a flood of failed logins cannot fill the audit log.
+pub struct AnonymousEventLimit {
+    per_minute: u32,
+    /// Start of the current one-minute window and events recorded in it
+    windows: Mutex<HashMap<String, (DateTime<Utc>, u32)>>,
+}
+
+impl AnonymousEventLimit {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether another event from `source_ip` may be recorded at `now`.
+    pub fn allow(&self, source_ip: &str, now: DateTime<Utc>) -> bool {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        windows.retain(|_, (start, _)| now - *start < Duration::minutes(1));
+
+        let (_, count) = windows.entry(source_ip.to_string()).or_insert((now, 0));
+        *count += 1;
+        if *count == self.per_minute + 1 {
+            warn!(
+                "Dropping audit events of anonymous requests from {} for the rest of the minute",
+                source_ip
+            );
+        }
+        *count <= self.per_minute
+    }
+}
+
+/// Records mutating requests (anything but GET, HEAD and OPTIONS) in the
+/// audit log, including ones rejected by authentication. Requests that
+/// neither matched a route nor came from a known caller are skipped, and
+/// those without a known user are subject to [`AnonymousEventLimit`].
+pub async fn record_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    if matches!(method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+
+    let resource = request.uri().path().to_string();
+    let source_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+    let response = next.run(request).await;
+
+    let actor = response.extensions().get::<AuditActor>().cloned();
+    if actor.is_none() && response.extensions().get::<Routed>().is_none() {
+        return response;
+    }
+    let actor = actor.unwrap_or_default();
+    if actor.user_id.is_none()
+        && !state
+            .audit_limit
+            .allow(source_ip.as_deref().unwrap_or("unknown"), Utc::now())
+    {
+        return response;
+    }
+
+    let status = response.status();
+
+    let event = NewAuditEvent {
+        user_id: actor.user_id,
+        username: actor.username,
+        action: method.to_string(),
+        resource,
+        success: status.is_success() || status.is_redirection(),
+        status_code: status.as_u16(),
+        source_ip,
+    };
+
+    // A failed audit write is logged loudly but must not undo the request
+    if let Err(e) = AuditService::new(state.db.clone()).record(event).await {
+        error!("Failed to record audit event: {}", e);
+    }
+
+    response
+}
+
+/// Deletes audit events older than `audit.retention_days` once an hour.
+pub fn spawn_audit_purger(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let audit = AuditService::new(state.db.clone());
+
+        loop {
+            interval.tick().await;
+            match audit
+                .purge(Utc::now(), state.config.audit.retention_days)
+                .await
+            {
+                Ok(0) => {}
+                Ok(removed) => info!("Expired {} audit events", removed),
+                Err(e) => warn!("Failed to expire audit events: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode};
+    use sqlx::query_scalar;
+    use tower::ServiceExt;
+
+    async fn events(state: &AppState) -> i64 {
+        query_scalar("SELECT COUNT(*) FROM audit_events")
+            .fetch_one(state.db.pool())
+            .await
+            .unwrap()
+    }
+
+    async fn post(state: &AppState, uri: &str, body: &str) -> StatusCode {
+        let mut request = axum::http::Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 4000))));
+        crate::create_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn skips_anonymous_requests_for_unknown_paths() {
+        let state = crate::test_state().await;
+
+        assert_eq!(
+            post(&state, "/api/v1/no-such-route", "{}").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            post(&state, "/elsewhere", "{}").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(events(&state).await, 0);
+
+        // A rejected call of a real route is still worth recording
+        assert_eq!(
+            post(&state, "/api/v1/users", "{}").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(events(&state).await, 1);
+    }
+
+    #[tokio::test]
+    async fn limits_anonymous_events_per_source_ip() {
+        let state = crate::test_state().await;
+        let per_minute = state.config.audit.anonymous_events_per_minute as i64;
+
+        // Failed logins and unauthenticated calls share the allowance; the
+        // calls are cheaper to make than logins, which run bcrypt
+        let login = r#"{"username":"nobody","password":"wrong"}"#;
+        post(&state, "/api/v1/auth/login", login).await;
+        for _ in 0..per_minute + 5 {
+            post(&state, "/api/v1/users", "{}").await;
+        }
+        assert_eq!(events(&state).await, per_minute);
+    }
+
+    #[test]
+    fn anonymous_limit_resets_every_minute() {
+        let limit = AnonymousEventLimit::new(2);
+        let now: DateTime<Utc> = "2026-01-01T12:00:00Z".parse().unwrap();
+
+        assert!(limit.allow("192.0.2.1", now));
+        assert!(limit.allow("192.0.2.1", now));
+        assert!(!limit.allow("192.0.2.1", now + Duration::seconds(30)));
+        // Counted per address
+        assert!(limit.allow("192.0.2.2", now + Duration::seconds(30)));
+
+        assert!(limit.allow("192.0.2.1", now + Duration::minutes(1)));
+    }
+}
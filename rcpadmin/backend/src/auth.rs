@@ -1,117 +1,368 @@
 use axum::{
-    extract::{Request, State},
+    extract::{Query, Request, State},
+    http::{HeaderMap, Uri},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::{
+    audit::AuditActor,
     error::{AppError, Result},
-    models::{User, UserRole},
+    models::{Permission, User},
+    services::{
+        api_key::ApiKeyService, auth::AuthService, permission::PermissionService, totp::TotpService,
+    },
     AppState,
 };
 
+/// How often a WebSocket re-checks the credentials it was opened with
+const WS_REAUTH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String, // User ID
     pub username: String,
     pub role: String,
-    pub exp: i64, // Expiration time
-    pub iat: i64, // Issued at
+    pub exp: i64,    // Expiration time
+    pub iat: i64,    // Issued at
+    pub jti: String, // Token ID, checked against the revocation list
 }
 
-pub async fn protect(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
-    // Extract the Authorization header manually
-    let auth_header = match request.headers().get("authorization") {
-        Some(header) => header,
-        None => return AppError::Auth("Missing authorization header".to_string()).into_response(),
-    };
+/// Permissions granted to the authenticated user's role, inserted into the
+/// request extensions by [`protect`].
+#[derive(Debug, Clone)]
+pub struct GrantedPermissions(pub HashSet<Permission>);
 
-    let auth_str = match auth_header.to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            return AppError::Auth("Invalid authorization header".to_string()).into_response()
-        }
+impl GrantedPermissions {
+    pub fn contains(&self, permission: Permission) -> bool {
+        self.0.contains(&permission)
+    }
+}
+
+/// What a request authenticated with.
+#[derive(Clone)]
+enum Credentials {
+    /// A JWT access token from `login`
+    Bearer(String),
+    /// An automation key, see [`ApiKeyService`]
+    ApiKey(String),
+}
+
+/// Who a request runs as, once its credentials checked out.
+struct Principal {
+    claims: Claims,
+    permissions: HashSet<Permission>,
+    actor: AuditActor,
+}
+
+pub async fn protect(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let credentials = match request_credentials(request.headers()) {
+        Ok(credentials) => credentials,
+        Err(err) => return err.into_response(),
     };
 
-    let token = if auth_str.starts_with("Bearer ") {
-        &auth_str[7..]
-    } else {
-        return AppError::Auth("Invalid authorization format".to_string()).into_response();
+    authenticate_request(&state, credentials, request, next).await
+}
+
+/// Variant of [`protect`] for WebSocket upgrades. Browsers cannot set headers
+/// on a WebSocket handshake, so the token may also be passed as the
+/// `access_token` query parameter; [`redact_access_token`] keeps it out of
+/// the logs. Inserts a [`WsAccess`] for the socket to watch.
+pub async fn protect_ws(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let query_token = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(mut params)| params.remove("access_token"));
+
+    let credentials = match query_token {
+        Some(token) => Credentials::Bearer(token),
+        None => match request_credentials(request.headers()) {
+            Ok(credentials) => credentials,
+            Err(err) => return err.into_response(),
+        },
     };
 
-    match validate_token(token, &state.config.jwt_secret) {
-        Ok(claims) => {
-            // Verify the token is valid
-            match Uuid::parse_str(&claims.sub) {
-                Ok(_user_id) => {
-                    // Insert claims into request extensions for use in handlers
-                    request.extensions_mut().insert(claims);
-                    next.run(request).await
-                }
-                Err(_) => AppError::Auth("Invalid token".to_string()).into_response(),
+    request.extensions_mut().insert(WsAccess {
+        state: state.clone(),
+        credentials: credentials.clone(),
+    });
+    authenticate_request(&state, credentials, request, next).await
+}
+
+/// The credentials a WebSocket was opened with. Authentication happens once
+/// at the upgrade, so long-lived sockets watch [`WsAccess::revoked`] to close
+/// after a logout, password change, disabled account or revoked API key.
+#[derive(Clone)]
+pub struct WsAccess {
+    state: AppState,
+    credentials: Credentials,
+}
+
+impl WsAccess {
+    /// Checks the credentials every [`WS_REAUTH_INTERVAL`] and resolves with
+    /// the reason once they are no longer accepted, including when the
+    /// token expires. Database errors do not end the socket.
+    pub async fn revoked(&self) -> AppError {
+        let mut interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + WS_REAUTH_INTERVAL,
+            WS_REAUTH_INTERVAL,
+        );
+        loop {
+            interval.tick().await;
+            let result = match &self.credentials {
+                Credentials::Bearer(token) => authorize_user(&self.state, token).await,
+                Credentials::ApiKey(key) => authorize_api_key(&self.state, key).await,
+            };
+            match result {
+                Ok(_) | Err(AppError::Database(_)) | Err(AppError::Internal(_)) => {}
+                Err(err) => return err,
             }
         }
-        Err(err) => err.into_response(),
     }
 }
 
-pub async fn require_admin(
-    State(state): State<AppState>,
+/// `uri` as it may be logged: the value of an `access_token` query
+/// parameter is replaced, since it is a live bearer token.
+pub fn redact_access_token(uri: &Uri) -> String {
+    match uri.query() {
+        Some(query) if query.contains("access_token") => {
+            let query: Vec<&str> = query
+                .split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some(("access_token", _)) => "access_token=[redacted]",
+                    _ => pair,
+                })
+                .collect();
+            format!("{}?{}", uri.path(), query.join("&"))
+        }
+        _ => uri.to_string(),
+    }
+}
+
+async fn authenticate_request(
+    state: &AppState,
+    credentials: Credentials,
     mut request: Request,
     next: Next,
 ) -> Response {
-    // Extract the Authorization header manually
-    let auth_header = match request.headers().get("authorization") {
-        Some(header) => header,
-        None => return AppError::Auth("Missing authorization header".to_string()).into_response(),
+    let principal = match credentials {
+        Credentials::Bearer(token) => authorize_user(state, &token).await,
+        Credentials::ApiKey(key) => authorize_api_key(state, &key).await,
+    };
+    let Principal {
+        claims,
+        permissions,
+        actor,
+    } = match principal {
+        Ok(principal) => principal,
+        Err(err) => return err.into_response(),
     };
 
-    let auth_str = match auth_header.to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            return AppError::Auth("Invalid authorization header".to_string()).into_response()
-        }
+    // Insert claims and permissions into request extensions for use in handlers
+    request.extensions_mut().insert(claims);
+    request
+        .extensions_mut()
+        .insert(GrantedPermissions(permissions));
+
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(actor);
+    response
+}
+
+async fn authorize_user(state: &AppState, token: &str) -> Result<Principal> {
+    let (claims, user) = authorize_token(state, token).await?;
+
+    // Members of a role that requires 2FA can only reach the enrollment
+    // endpoints under /auth, which do not go through this middleware
+    if TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .enrollment_required(&user)
+        .await?
+    {
+        return Err(AppError::TotpEnrollmentRequired);
+    }
+
+    let permissions = PermissionService::new(state.db.clone())
+        .role_permissions(&user.role)
+        .await?;
+
+    Ok(Principal {
+        actor: AuditActor::from(&claims),
+        claims,
+        permissions,
+    })
+}
+
+/// An API key acts for the user who created it, with the permissions both
+/// the key and the user's current role allow. Disabling the user disables
+/// their keys too, as does a role requiring 2FA the user has not enabled.
+async fn authorize_api_key(state: &AppState, key: &str) -> Result<Principal> {
+    let now = Utc::now();
+    let api_key = ApiKeyService::new(state.db.clone())
+        .authenticate(key, now)
+        .await?;
+
+    let owner_id = Uuid::parse_str(&api_key.user_id)
+        .map_err(|_| AppError::Auth("Invalid API key".to_string()))?;
+    let owner = AuthService::new(state.db.clone())
+        .get_user_by_id(owner_id)
+        .await
+        .map_err(|err| match err {
+            AppError::NotFound(_) => AppError::AccountDisabled(
+                "The API key's owner is disabled or no longer exists".to_string(),
+            ),
+            other => other,
+        })?;
+
+    // Otherwise a key would let its owner skip mandatory 2FA. Creating a key
+    // passes through here or `authorize_user`, so that is refused as well.
+    if TotpService::new(state.db.clone(), &state.config.auth.totp_issuer)
+        .enrollment_required(&owner)
+        .await?
+    {
+        return Err(AppError::TotpEnrollmentRequired);
+    }
+
+    let role_permissions = PermissionService::new(state.db.clone())
+        .role_permissions(&owner.role)
+        .await?;
+    let permissions = api_key
+        .permissions()
+        .into_iter()
+        .filter(|p| role_permissions.contains(p))
+        .collect();
+
+    let claims = Claims {
+        sub: owner.id.to_string(),
+        username: owner.username.clone(),
+        role: owner.role.to_string(),
+        exp: api_key.expires_at.timestamp(),
+        iat: api_key.created_at.timestamp(),
+        jti: api_key.id.clone(),
+    };
+    // The key's name, so the audit trail tells automation from its owner
+    let actor = AuditActor {
+        user_id: Some(owner.id.to_string()),
+        username: Some(format!("api-key:{}", api_key.name)),
     };
 
-    let token = if auth_str.starts_with("Bearer ") {
-        &auth_str[7..]
+    Ok(Principal {
+        claims,
+        permissions,
+        actor,
+    })
+}
+
+/// Route layer that rejects the request unless the caller was granted the
+/// permission it is constructed with. Must run inside [`protect`].
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let granted = request
+        .extensions()
+        .get::<GrantedPermissions>()
+        .is_some_and(|granted| granted.contains(permission));
+
+    if granted {
+        next.run(request).await
     } else {
-        return AppError::Auth("Invalid authorization format".to_string()).into_response();
-    };
+        AppError::Unauthorized(format!("Permission '{}' required", permission)).into_response()
+    }
+}
 
-    match validate_token(token, &state.config.jwt_secret) {
-        Ok(claims) => {
-            // Check if user has admin role
-            if claims.role != UserRole::Admin.to_string() {
-                AppError::Unauthorized("Admin role required".to_string()).into_response()
-            } else {
-                // Insert claims into request extensions for use in handlers
-                request.extensions_mut().insert(claims);
-                next.run(request).await
+/// Validates the bearer token in `headers` and loads the user it was issued to.
+///
+/// The user is re-read from the database on every call so that disabling or
+/// deleting an account takes effect immediately, even for tokens that have not
+/// expired yet. Tokens revoked by a logout or a password change are rejected
+/// the same way. The returned claims carry the user's current role.
+pub async fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(Claims, User)> {
+    authorize_token(state, bearer_token(headers)?).await
+}
+
+/// Same as [`authorize`], for a token that did not come from the
+/// `Authorization` header.
+pub async fn authorize_token(state: &AppState, token: &str) -> Result<(Claims, User)> {
+    let mut claims = validate_token(token, &state.config.auth.jwt_secret)?;
+
+    let user_id =
+        Uuid::parse_str(&claims.sub).map_err(|_| AppError::Auth("Invalid token".to_string()))?;
+
+    let auth_service = AuthService::new(state.db.clone());
+    if auth_service.is_token_revoked(&claims).await? {
+        return Err(AppError::TokenRevoked("Token has been revoked".to_string()));
+    }
+
+    let user = auth_service
+        .get_user_by_id(user_id)
+        .await
+        .map_err(|err| match err {
+            AppError::NotFound(_) => {
+                AppError::AccountDisabled("Account is disabled or no longer exists".to_string())
             }
-        }
-        Err(err) => err.into_response(),
+            other => other,
+        })?;
+
+    claims.role = user.role.to_string();
+    Ok((claims, user))
+}
+
+fn request_credentials(headers: &HeaderMap) -> Result<Credentials> {
+    let auth_header = headers.get("authorization").ok_or(AppError::MissingToken)?;
+
+    let auth_str = auth_header
+        .to_str()
+        .map_err(|_| AppError::Auth("Invalid authorization header".to_string()))?;
+
+    if let Some(key) = auth_str.strip_prefix("ApiKey ") {
+        Ok(Credentials::ApiKey(key.to_string()))
+    } else if let Some(token) = auth_str.strip_prefix("Bearer ") {
+        Ok(Credentials::Bearer(token.to_string()))
+    } else {
+        Err(AppError::Auth("Invalid authorization format".to_string()))
     }
 }
 
+pub fn bearer_token(headers: &HeaderMap) -> Result<&str> {
+    let auth_header = headers.get("authorization").ok_or(AppError::MissingToken)?;
+
+    let auth_str = auth_header
+        .to_str()
+        .map_err(|_| AppError::Auth("Invalid authorization header".to_string()))?;
+
+    auth_str
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| AppError::Auth("Invalid authorization format".to_string()))
+}
+
 pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
     let decoded = decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
         &Validation::default(),
     )
-    .map_err(|e| AppError::Auth(format!("Invalid token: {}", e)))?;
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => AppError::TokenExpired("Token has expired".to_string()),
+        _ => AppError::Auth(format!("Invalid token: {}", e)),
+    })?;
 
     Ok(decoded.claims)
 }
 
-pub fn generate_token(user: &User, secret: &str, expires_in_days: i64) -> Result<String> {
+pub fn generate_token(user: &User, secret: &str, expires_in: Duration) -> Result<String> {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::days(expires_in_days))
+        .checked_add_signed(expires_in)
         .expect("Valid timestamp")
         .timestamp();
 
@@ -123,6 +374,7 @@ pub fn generate_token(user: &User, secret: &str, expires_in_days: i64) -> Result
         role: user.role.to_string(),
         exp: expiration,
         iat: issued_at,
+        jti: Uuid::new_v4().to_string(),
     };
 
     let token = encode(
@@ -134,3 +386,88 @@ pub fn generate_token(user: &User, secret: &str, expires_in_days: i64) -> Result
 
     Ok(token)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateApiKey, UserRole};
+
+    #[test]
+    fn redacts_access_token_from_logged_uri() {
+        let uri: Uri = "/ws/logs?level=warn&access_token=eyJhbGciOi.x.y&limit=5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            redact_access_token(&uri),
+            "/ws/logs?level=warn&access_token=[redacted]&limit=5"
+        );
+
+        let uri: Uri = "/api/v1/system/logs?q=token".parse().unwrap();
+        assert_eq!(redact_access_token(&uri), "/api/v1/system/logs?q=token");
+    }
+
+    async fn api_key(state: &AppState, permissions: &[Permission]) -> String {
+        let admin = AuthService::new(state.db.clone())
+            .get_user_by_id("00000000-0000-0000-0000-000000000001".parse().unwrap())
+            .await
+            .unwrap();
+        let granted = PermissionService::new(state.db.clone())
+            .role_permissions(&admin.role)
+            .await
+            .unwrap();
+        let request = CreateApiKey {
+            name: "ci".to_string(),
+            permissions: permissions.to_vec(),
+            expires_in_days: None,
+        };
+        let (key, _) = ApiKeyService::new(state.db.clone())
+            .create(admin.id, &granted, &request)
+            .await
+            .unwrap();
+        key
+    }
+
+    #[tokio::test]
+    async fn api_keys_never_exceed_their_owner_role() {
+        let state = crate::test_state().await;
+        let key = api_key(&state, &[Permission::AuditRead, Permission::UsersManage]).await;
+
+        // The owner's role loses one of the key's permissions later on
+        let kept: Vec<_> = PermissionService::new(state.db.clone())
+            .role_permissions(&UserRole::Admin)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|p| *p != Permission::AuditRead)
+            .collect();
+        PermissionService::new(state.db.clone())
+            .set_role_permissions(&UserRole::Admin, &kept)
+            .await
+            .unwrap();
+
+        let Ok(principal) = authorize_api_key(&state, &key).await else {
+            panic!("API key refused");
+        };
+        assert_eq!(
+            principal.permissions,
+            HashSet::from([Permission::UsersManage])
+        );
+        assert_eq!(principal.claims.username, "admin");
+        assert_eq!(principal.actor.username.as_deref(), Some("api-key:ci"));
+    }
+
+    #[tokio::test]
+    async fn api_keys_need_their_owner_enrolled_in_required_2fa() {
+        let state = crate::test_state().await;
+        let key = api_key(&state, &[Permission::AuditRead]).await;
+
+        PermissionService::new(state.db.clone())
+            .set_requires_totp(&UserRole::Admin, true)
+            .await
+            .unwrap();
+        assert!(matches!(
+            authorize_api_key(&state, &key).await,
+            Err(AppError::TotpEnrollmentRequired)
+        ));
+    }
+}
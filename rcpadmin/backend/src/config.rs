@@ -1,31 +1,672 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::{HeaderName, Method};
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::{
+    env, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::{info, warn};
 
+/// Read from the working directory when no file is named explicitly
+const DEFAULT_CONFIG_FILE: &str = "rcpadmin.toml";
+
+/// Secrets that ship in examples and docs; never accepted outside dev mode
+const PLACEHOLDER_JWT_SECRETS: [&str; 2] = [
+    "change-me-in-production",
+    "your-secret-key-here-change-in-production",
+];
+const MIN_JWT_SECRET_LEN: usize = 32;
+/// bcrypt only hashes this many bytes of a password
+pub const MAX_PASSWORD_BYTES: usize = 72;
+
+/// Backend settings. Built from the defaults below, overlaid by the TOML
+/// config file, overlaid by environment variables.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub database_url: String,
     pub bind_address: String,
-    pub rcpdaemon_url: String,
+    /// Relaxes the startup checks meant for production, e.g. allows a
+    /// placeholder JWT secret
+    pub dev_mode: bool,
+    /// Built web UI, served for every path outside the API. `None` disables
+    /// static file serving.
+    pub static_dir: Option<PathBuf>,
+    pub cors: CorsConfig,
+    pub auth: AuthConfig,
+    pub tls: TlsConfig,
+    pub rcpdaemon: DaemonConfig,
+    pub metrics: MetricsConfig,
+    pub audit: AuditConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub mode: CorsMode,
+    /// Methods allowed for origins that do not list their own
+    pub methods: Vec<String>,
+    /// Request headers allowed for origins that do not list their own
+    pub headers: Vec<String>,
+    pub origins: Vec<CorsOrigin>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorsMode {
+    /// Listed origins get CORS headers; others get none, so browsers block them
+    AllowList,
+    /// Every cross-origin request is refused outright; for when the web UI
+    /// is served from `static_dir` on the same origin as the API
+    SameOrigin,
+}
+
+impl FromStr for CorsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "allow_list" => Ok(CorsMode::AllowList),
+            "same_origin" => Ok(CorsMode::SameOrigin),
+            _ => Err(format!("Unknown CORS mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsOrigin {
+    /// `scheme://host[:port]`, exactly as browsers send it
+    pub origin: String,
+    pub methods: Option<Vec<String>>,
+    pub headers: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
     pub jwt_secret: String,
-    pub cors_origins: Vec<String>,
+    /// Access tokens are short-lived; clients renew them with a refresh token
+    pub access_token_ttl_minutes: i64,
+    pub refresh_token_ttl_days: i64,
+    /// Shown as the account's issuer in authenticator apps
+    pub totp_issuer: String,
+    pub lockout: LockoutConfig,
+    pub password: PasswordConfig,
+}
+
+/// Throttling of failed logins, counted separately per client IP and per
+/// username. Past `free_attempts` each failure blocks further attempts for
+/// an exponentially growing delay; at `lockout_after` failures the block
+/// lasts `lockout_minutes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LockoutConfig {
+    pub free_attempts: u32,
+    pub backoff_base_secs: i64,
+    pub backoff_max_secs: i64,
+    pub lockout_after: u32,
+    pub lockout_minutes: i64,
+    /// A quiet period after which the failure count starts over
+    pub reset_after_minutes: i64,
+}
+
+/// Rules a new password must satisfy, whether set by an admin, changed by
+/// its owner or set through a reset token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordConfig {
+    /// In characters. Passwords longer than [`MAX_PASSWORD_BYTES`] bytes are
+    /// always refused.
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    /// Anything that is not a letter or digit
+    pub require_symbol: bool,
+    /// Refuse passwords that contain the username
+    pub reject_username: bool,
+    /// Lifetime of the reset tokens an admin hands out
+    pub reset_token_ttl_minutes: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM certificate chain
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key
+    pub key_path: Option<PathBuf>,
+    /// PEM CA bundle; when set, every client must present a certificate
+    /// signed by one of these CAs (mutual TLS). This only restricts who can
+    /// connect: requests still need a token or API key
+    pub client_ca_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Registered as the `default` daemon on the first start; after that the
+    /// fleet is managed through `/api/v1/daemons`
+    pub url: String,
+    pub token: Option<String>,
+    /// The settings below apply to every daemon in the fleet
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub max_retries: u32,
+    /// How often each daemon's status is checked
+    pub health_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub sample_interval_secs: u64,
+    pub retention_days: i64,
+    /// Bearer token required by `/metrics`, which is disabled without one
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    pub retention_days: i64,
+    /// Events recorded per client IP and minute for requests without a known
+    /// user, such as failed logins; the rest are dropped
+    pub anonymous_events_per_minute: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: "sqlite:./rcpadmin.db".to_string(),
+            bind_address: "127.0.0.1:8080".to_string(),
+            dev_mode: false,
+            static_dir: Some(PathBuf::from("../web/dist")),
+            cors: CorsConfig::default(),
+            auth: AuthConfig::default(),
+            tls: TlsConfig::default(),
+            rcpdaemon: DaemonConfig::default(),
+            metrics: MetricsConfig::default(),
+            audit: AuditConfig::default(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            mode: CorsMode::AllowList,
+            methods: ["GET", "POST", "PUT", "DELETE"].map(String::from).to_vec(),
+            headers: ["authorization", "content-type", "x-request-id"]
+                .map(String::from)
+                .to_vec(),
+            origins: Vec::new(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwt_secret: String::new(),
+            access_token_ttl_minutes: 15,
+            refresh_token_ttl_days: 7,
+            totp_issuer: "RCP Admin".to_string(),
+            lockout: LockoutConfig::default(),
+            password: PasswordConfig::default(),
+        }
+    }
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            backoff_base_secs: 1,
+            backoff_max_secs: 60,
+            lockout_after: 10,
+            lockout_minutes: 15,
+            reset_after_minutes: 15,
+        }
+    }
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 10,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_username: true,
+            reset_token_ttl_minutes: 60,
+        }
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:3030".to_string(),
+            token: None,
+            timeout_secs: 10,
+            connect_timeout_secs: 3,
+            max_retries: 2,
+            health_interval_secs: 15,
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_secs: 10,
+            retention_days: 90,
+            token: None,
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 365,
+            anonymous_events_per_minute: 30,
+        }
+    }
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self> {
-        Ok(Self {
-            database_url: env::var("DATABASE_URL")
-                .unwrap_or_else(|_| "sqlite:./rcpadmin.db".to_string()),
-            bind_address: env::var("BIND_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8080".to_string()),
-            rcpdaemon_url: env::var("RCPDAEMON_URL")
-                .unwrap_or_else(|_| "http://127.0.0.1:3030".to_string()),
-            jwt_secret: env::var("JWT_SECRET")
-                .unwrap_or_else(|_| "change-me-in-production".to_string()),
-            cors_origins: env::var("CORS_ORIGINS")
-                .unwrap_or_else(|_| "*".to_string())
+    /// Loads the file named by `--config <path>` or `RCPADMIN_CONFIG`, else
+    /// `rcpadmin.toml` if present, then applies env overrides and validates.
+    pub fn load() -> Result<Self> {
+        let mut config = match config_file()? {
+            Some(path) => {
+                info!("Reading configuration from {}", path.display());
+                Self::from_file(&path)?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Environment variables win over the file, so deployments can inject
+    /// secrets without writing them to disk.
+    fn apply_env(&mut self) -> Result<()> {
+        env_override("DATABASE_URL", &mut self.database_url)?;
+        env_override("BIND_ADDRESS", &mut self.bind_address)?;
+        env_override("RCPADMIN_DEV_MODE", &mut self.dev_mode)?;
+        env_override_opt("STATIC_DIR", &mut self.static_dir)?;
+        env_override("CORS_MODE", &mut self.cors.mode)?;
+        if let Ok(origins) = env::var("CORS_ORIGINS") {
+            self.cors.origins = origins
                 .split(',')
-                .map(|s| s.trim().to_string())
-                .collect(),
-        })
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(|origin| CorsOrigin {
+                    origin: origin.to_string(),
+                    methods: None,
+                    headers: None,
+                })
+                .collect();
+        }
+
+        env_override("JWT_SECRET", &mut self.auth.jwt_secret)?;
+        env_override(
+            "ACCESS_TOKEN_TTL_MINUTES",
+            &mut self.auth.access_token_ttl_minutes,
+        )?;
+        env_override(
+            "REFRESH_TOKEN_TTL_DAYS",
+            &mut self.auth.refresh_token_ttl_days,
+        )?;
+
+        env_override("TOTP_ISSUER", &mut self.auth.totp_issuer)?;
+        env_override("LOGIN_LOCKOUT_AFTER", &mut self.auth.lockout.lockout_after)?;
+        env_override(
+            "LOGIN_LOCKOUT_MINUTES",
+            &mut self.auth.lockout.lockout_minutes,
+        )?;
+        env_override("PASSWORD_MIN_LENGTH", &mut self.auth.password.min_length)?;
+
+        env_override("TLS_ENABLED", &mut self.tls.enabled)?;
+        env_override_opt("TLS_CERT_PATH", &mut self.tls.cert_path)?;
+        env_override_opt("TLS_KEY_PATH", &mut self.tls.key_path)?;
+        env_override_opt("TLS_CLIENT_CA_PATH", &mut self.tls.client_ca_path)?;
+
+        env_override("RCPDAEMON_URL", &mut self.rcpdaemon.url)?;
+        env_override_opt("RCPDAEMON_TOKEN", &mut self.rcpdaemon.token)?;
+        env_override("RCPDAEMON_TIMEOUT_SECS", &mut self.rcpdaemon.timeout_secs)?;
+        env_override(
+            "RCPDAEMON_CONNECT_TIMEOUT_SECS",
+            &mut self.rcpdaemon.connect_timeout_secs,
+        )?;
+        env_override("RCPDAEMON_MAX_RETRIES", &mut self.rcpdaemon.max_retries)?;
+        env_override(
+            "RCPDAEMON_HEALTH_INTERVAL_SECS",
+            &mut self.rcpdaemon.health_interval_secs,
+        )?;
+
+        env_override(
+            "METRICS_SAMPLE_INTERVAL_SECS",
+            &mut self.metrics.sample_interval_secs,
+        )?;
+        env_override("METRICS_RETENTION_DAYS", &mut self.metrics.retention_days)?;
+        env_override_opt("METRICS_TOKEN", &mut self.metrics.token)?;
+
+        env_override("AUDIT_RETENTION_DAYS", &mut self.audit.retention_days)?;
+        env_override(
+            "AUDIT_ANONYMOUS_EVENTS_PER_MINUTE",
+            &mut self.audit.anonymous_events_per_minute,
+        )?;
+        Ok(())
+    }
+
+    /// Reports every problem at once rather than stopping at the first.
+    fn validate(&mut self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if self.bind_address.parse::<SocketAddr>().is_err() {
+            errors.push(format!(
+                "bind_address '{}' is not a valid socket address",
+                self.bind_address
+            ));
+        }
+        if self.database_url.trim().is_empty() {
+            errors.push("database_url must not be empty".to_string());
+        }
+
+        self.validate_jwt_secret(&mut errors);
+        self.validate_cors(&mut errors);
+
+        if self.auth.access_token_ttl_minutes < 1 {
+            errors.push("auth.access_token_ttl_minutes must be at least 1".to_string());
+        }
+        if self.auth.refresh_token_ttl_days < 1 {
+            errors.push("auth.refresh_token_ttl_days must be at least 1".to_string());
+        } else if self.auth.refresh_token_ttl_days.saturating_mul(24 * 60)
+            <= self.auth.access_token_ttl_minutes
+        {
+            errors.push(
+                "auth.refresh_token_ttl_days must outlast auth.access_token_ttl_minutes"
+                    .to_string(),
+            );
+        }
+
+        // The issuer is a label in the otpauth:// URI, where ':' is the separator
+        if self.auth.totp_issuer.trim().is_empty() || self.auth.totp_issuer.contains(':') {
+            errors.push("auth.totp_issuer must be non-empty and must not contain ':'".to_string());
+        }
+
+        let lockout = &self.auth.lockout;
+        if lockout.lockout_after <= lockout.free_attempts {
+            errors.push(
+                "auth.lockout.lockout_after must be greater than auth.lockout.free_attempts"
+                    .to_string(),
+            );
+        }
+        for (name, value) in [
+            ("backoff_base_secs", lockout.backoff_base_secs),
+            ("backoff_max_secs", lockout.backoff_max_secs),
+            ("lockout_minutes", lockout.lockout_minutes),
+            ("reset_after_minutes", lockout.reset_after_minutes),
+        ] {
+            if value < 1 {
+                errors.push(format!("auth.lockout.{} must be at least 1", name));
+            }
+        }
+
+        let password = &self.auth.password;
+        if !(1..=MAX_PASSWORD_BYTES).contains(&password.min_length) {
+            errors.push(format!(
+                "auth.password.min_length must be between 1 and {}",
+                MAX_PASSWORD_BYTES
+            ));
+        }
+        if password.reset_token_ttl_minutes < 1 {
+            errors.push("auth.password.reset_token_ttl_minutes must be at least 1".to_string());
+        }
+
+        if self.tls.enabled {
+            for (name, path) in [
+                ("tls.cert_path", &self.tls.cert_path),
+                ("tls.key_path", &self.tls.key_path),
+            ] {
+                match path {
+                    None => errors.push(format!("{} is required when TLS is enabled", name)),
+                    Some(path) if !path.is_file() => {
+                        errors.push(format!("{} '{}' does not exist", name, path.display()))
+                    }
+                    Some(_) => {}
+                }
+            }
+            if let Some(path) = &self.tls.client_ca_path {
+                if !path.is_file() {
+                    errors.push(format!(
+                        "tls.client_ca_path '{}' does not exist",
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        if !self.rcpdaemon.url.starts_with("http://") && !self.rcpdaemon.url.starts_with("https://")
+        {
+            errors.push(format!(
+                "rcpdaemon.url '{}' must be an http:// or https:// URL",
+                self.rcpdaemon.url
+            ));
+        }
+        if self.rcpdaemon.timeout_secs == 0 || self.rcpdaemon.connect_timeout_secs == 0 {
+            errors.push(
+                "rcpdaemon.timeout_secs and rcpdaemon.connect_timeout_secs must be at least 1"
+                    .to_string(),
+            );
+        }
+        if self.rcpdaemon.health_interval_secs == 0 {
+            errors.push("rcpdaemon.health_interval_secs must be at least 1".to_string());
+        }
+
+        if self.metrics.sample_interval_secs == 0 {
+            errors.push("metrics.sample_interval_secs must be at least 1".to_string());
+        }
+        if self.metrics.retention_days < 1 {
+            errors.push("metrics.retention_days must be at least 1".to_string());
+        }
+        if self.audit.retention_days < 1 {
+            errors.push("audit.retention_days must be at least 1".to_string());
+        }
+
+        // A missing UI is not fatal; the API works without it
+        if let Some(dir) = &self.static_dir {
+            if !dir.is_dir() {
+                warn!(
+                    "static_dir '{}' does not exist; the web UI will not be served",
+                    dir.display()
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            bail!("Invalid configuration:\n  - {}", errors.join("\n  - "))
+        }
+    }
+
+    fn validate_cors(&self, errors: &mut Vec<String>) {
+        let cors = &self.cors;
+        if cors.mode == CorsMode::SameOrigin && !cors.origins.is_empty() {
+            warn!("cors.origins is ignored in same_origin mode");
+        }
+
+        for origin in &cors.origins {
+            if !is_valid_origin(&origin.origin) {
+                errors.push(format!(
+                    "cors.origins: '{}' is not an origin like https://admin.example.com (wildcards are not allowed)",
+                    origin.origin
+                ));
+            }
+        }
+
+        let all_methods = cors
+            .origins
+            .iter()
+            .filter_map(|o| o.methods.as_ref())
+            .chain([&cors.methods])
+            .flatten();
+        for method in all_methods {
+            if method.parse::<Method>().is_err() || method == "*" {
+                errors.push(format!("cors: '{}' is not an HTTP method", method));
+            }
+        }
+
+        let all_headers = cors
+            .origins
+            .iter()
+            .filter_map(|o| o.headers.as_ref())
+            .chain([&cors.headers])
+            .flatten();
+        for header in all_headers {
+            if header.parse::<HeaderName>().is_err() || header == "*" {
+                errors.push(format!("cors: '{}' is not a header name", header));
+            }
+        }
+    }
+
+    /// Tokens signed with a guessable secret can be forged, so outside dev
+    /// mode startup refuses anything short or copied from an example. A
+    /// missing secret is refused even in dev mode.
+    fn validate_jwt_secret(&self, errors: &mut Vec<String>) {
+        let secret = &self.auth.jwt_secret;
+        if secret.is_empty() {
+            errors.push("auth.jwt_secret (JWT_SECRET) must be set".to_string());
+            return;
+        }
+
+        let weak =
+            secret.len() < MIN_JWT_SECRET_LEN || PLACEHOLDER_JWT_SECRETS.contains(&secret.as_str());
+        if !weak {
+            return;
+        }
+
+        if self.dev_mode {
+            warn!("Using a weak JWT secret; acceptable in dev mode only");
+        } else {
+            errors.push(format!(
+                "auth.jwt_secret (JWT_SECRET) must be set to a random value of at least {} characters; set dev_mode to allow a weak secret locally",
+                MIN_JWT_SECRET_LEN
+            ));
+        }
+    }
+}
+
+/// `scheme://host[:port]` with nothing after the authority.
+fn is_valid_origin(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    matches!(scheme, "http" | "https")
+        && !authority.is_empty()
+        && !authority.contains(['/', '*', '?', '#', '@'])
+}
+
+/// Path named by `--config <path>`, `--config=<path>` or `RCPADMIN_CONFIG`,
+/// which must exist; otherwise `rcpadmin.toml` if it happens to.
+fn config_file() -> Result<Option<PathBuf>> {
+    let mut args = env::args().skip(1);
+    let mut explicit = None;
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            explicit = Some(
+                args.next()
+                    .ok_or_else(|| anyhow!("--config needs a path"))?,
+            );
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            explicit = Some(path.to_string());
+        }
+    }
+
+    match explicit.or_else(|| env::var("RCPADMIN_CONFIG").ok()) {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                bail!("Config file {} does not exist", path.display());
+            }
+            Ok(Some(path))
+        }
+        None => {
+            let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+            Ok(default.is_file().then_some(default))
+        }
+    }
+}
+
+fn env_override<T: FromStr>(name: &str, target: &mut T) -> Result<()> {
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .parse()
+            .map_err(|_| anyhow!("Invalid value for {}: {}", name, value))?;
+    }
+    Ok(())
+}
+
+/// Like [`env_override`]; an empty value clears the setting.
+fn env_override_opt<T: FromStr>(name: &str, target: &mut Option<T>) -> Result<()> {
+    match env::var(name) {
+        Ok(value) if value.is_empty() => *target = None,
+        Ok(value) => {
+            *target = Some(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid value for {}: {}", name, value))?,
+            )
+        }
+        Err(_) => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jwt_secret_errors(secret: &str, dev_mode: bool) -> Vec<String> {
+        let mut config = Config {
+            dev_mode,
+            ..Config::default()
+        };
+        config.auth.jwt_secret = secret.to_string();
+        let mut errors = Vec::new();
+        config.validate_jwt_secret(&mut errors);
+        errors
+    }
+
+    #[test]
+    fn weak_jwt_secret_needs_dev_mode() {
+        let placeholder = PLACEHOLDER_JWT_SECRETS[1];
+        assert_eq!(jwt_secret_errors(placeholder, false).len(), 1);
+        assert!(jwt_secret_errors(placeholder, true).is_empty());
+        assert!(jwt_secret_errors(&"x".repeat(MIN_JWT_SECRET_LEN), false).is_empty());
+    }
+
+    #[test]
+    fn empty_jwt_secret_is_refused_in_dev_mode() {
+        assert_eq!(jwt_secret_errors("", true).len(), 1);
+        assert_eq!(jwt_secret_errors("", false).len(), 1);
     }
 }
@@ -0,0 +1,227 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{collections::HashMap, sync::Arc};
+use tracing::debug;
+
+use crate::{
+    config::{CorsConfig, CorsMode},
+    error::AppError,
+    request_id::REQUEST_ID_HEADER,
+};
+
+/// How long browsers may cache a successful preflight
+const PREFLIGHT_MAX_AGE_SECS: &str = "600";
+
+/// What one allowed origin may do.
+struct OriginRule {
+    methods: Vec<Method>,
+    /// Lowercase header names
+    headers: Vec<String>,
+    allow_methods: HeaderValue,
+    allow_headers: HeaderValue,
+}
+
+/// CORS rules built once from [`CorsConfig`]. tower-http's `CorsLayer` only
+/// supports one set of methods and headers for all origins, hence our own.
+pub struct CorsPolicy {
+    mode: CorsMode,
+    /// Keyed by lowercase origin
+    origins: HashMap<String, OriginRule>,
+}
+
+impl CorsPolicy {
+    /// Expects a config that passed `Config::validate`.
+    pub fn new(config: &CorsConfig) -> Self {
+        let origins = config
+            .origins
+            .iter()
+            .map(|origin| {
+                let methods: Vec<String> = origin
+                    .methods
+                    .as_ref()
+                    .unwrap_or(&config.methods)
+                    .iter()
+                    .map(|m| m.to_ascii_uppercase())
+                    .collect();
+                let headers = origin.headers.as_ref().unwrap_or(&config.headers);
+                let rule = OriginRule {
+                    methods: methods.iter().filter_map(|m| m.parse().ok()).collect(),
+                    headers: headers.iter().map(|h| h.to_ascii_lowercase()).collect(),
+                    allow_methods: list_header(&methods),
+                    allow_headers: list_header(headers),
+                };
+                (origin.origin.to_ascii_lowercase(), rule)
+            })
+            .collect();
+
+        Self {
+            mode: config.mode,
+            origins,
+        }
+    }
+}
+
+fn list_header(values: &[String]) -> HeaderValue {
+    HeaderValue::from_str(&values.join(", ")).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+/// Applies the CORS policy. Requests without an `Origin`, or from the
+/// backend's own origin, pass through untouched.
+pub async fn apply_cors(
+    State(policy): State<Arc<CorsPolicy>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_ascii_lowercase)
+    else {
+        return next.run(request).await;
+    };
+
+    if is_same_origin(&origin, &request) {
+        return next.run(request).await;
+    }
+
+    if policy.mode == CorsMode::SameOrigin {
+        debug!("Refused cross-origin request from {}", origin);
+        return AppError::Unauthorized("Cross-origin requests are not allowed".to_string())
+            .into_response();
+    }
+
+    let rule = policy.origins.get(&origin);
+    let preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    if preflight {
+        return match rule {
+            Some(rule) if preflight_allowed(rule, request.headers()) => {
+                let mut response = StatusCode::NO_CONTENT.into_response();
+                let headers = response.headers_mut();
+                headers.insert(header::VARY, HeaderValue::from_static("origin"));
+                allow_origin(headers, &origin);
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_METHODS,
+                    rule.allow_methods.clone(),
+                );
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_HEADERS,
+                    rule.allow_headers.clone(),
+                );
+                headers.insert(
+                    header::ACCESS_CONTROL_MAX_AGE,
+                    HeaderValue::from_static(PREFLIGHT_MAX_AGE_SECS),
+                );
+                response
+            }
+            _ => {
+                debug!("Refused CORS preflight from {}", origin);
+                let mut response = StatusCode::FORBIDDEN.into_response();
+                response
+                    .headers_mut()
+                    .insert(header::VARY, HeaderValue::from_static("origin"));
+                response
+            }
+        };
+    }
+
+    let allowed = rule.is_some_and(|rule| rule.methods.contains(request.method()));
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    // Without CORS headers the browser keeps the response from the page
+    headers.append(header::VARY, HeaderValue::from_static("origin"));
+    if allowed {
+        allow_origin(headers, &origin);
+        headers.insert(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            REQUEST_ID_HEADER.into(),
+        );
+    }
+    response
+}
+
+fn allow_origin(headers: &mut HeaderMap, origin: &str) {
+    if let Ok(value) = HeaderValue::from_str(origin) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    }
+}
+
+fn preflight_allowed(rule: &OriginRule, headers: &HeaderMap) -> bool {
+    let method_allowed = headers
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|m| m.parse::<Method>().ok())
+        .is_some_and(|m| rule.methods.contains(&m));
+
+    let headers_allowed = headers
+        .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|h| h.trim().to_ascii_lowercase())
+        .filter(|h| !h.is_empty())
+        .all(|h| rule.headers.contains(&h));
+
+    method_allowed && headers_allowed
+}
+
+/// Browsers send `Origin` on same-origin POSTs too; those match our `Host`,
+/// or over HTTP/2 the `:authority` that hyper puts into the request URI.
+fn is_same_origin(origin: &str, request: &Request) -> bool {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| request.uri().authority().map(|a| a.as_str()));
+    let authority = origin.split_once("://").map(|(_, authority)| authority);
+    matches!((host, authority), (Some(host), Some(authority)) if host.eq_ignore_ascii_case(authority))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Version};
+
+    #[test]
+    fn same_origin_from_host_header() {
+        let request = Request::builder()
+            .version(Version::HTTP_11)
+            .uri("/api/v1/auth/login")
+            .header(header::HOST, "admin.example.com:8080")
+            .body(Body::empty())
+            .unwrap();
+
+        assert!(is_same_origin("https://admin.example.com:8080", &request));
+        assert!(!is_same_origin("https://evil.example.com", &request));
+    }
+
+    #[test]
+    fn same_origin_from_http2_authority() {
+        let request = Request::builder()
+            .version(Version::HTTP_2)
+            .uri("https://admin.example.com:8080/api/v1/auth/login")
+            .body(Body::empty())
+            .unwrap();
+
+        assert!(is_same_origin("https://admin.example.com:8080", &request));
+        assert!(!is_same_origin("https://evil.example.com", &request));
+    }
+
+    #[test]
+    fn no_host_is_not_same_origin() {
+        let request = Request::builder()
+            .uri("/api/v1/auth/login")
+            .body(Body::empty())
+            .unwrap();
+
+        assert!(!is_same_origin("https://admin.example.com", &request));
+    }
+}
@@ -1,5 +1,13 @@
-use crate::error::Result;
-use sqlx::sqlite::SqlitePool;
+use crate::error::{AppError, Result};
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+};
+use std::str::FromStr;
+use tracing::info;
+
+/// Migrations from `./migrations`, embedded into the binary at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 #[derive(Clone)]
 pub struct Database {
@@ -8,20 +16,61 @@ pub struct Database {
 
 impl Database {
     pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = SqlitePool::connect(database_url).await?;
+        let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
         Ok(Self { pool })
     }
 
     pub async fn migrate(&self) -> Result<()> {
-        sqlx::migrate!("./migrations")
+        self.check_schema_version().await?;
+
+        MIGRATOR
             .run(&self.pool)
             .await
-            .map_err(|e| {
-                crate::error::AppError::Internal(anyhow::anyhow!("Migration failed: {}", e))
-            })?;
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Migration failed: {}", e)))?;
+
+        info!(
+            "Database schema is at version {}",
+            self.schema_version().await?.unwrap_or_default()
+        );
         Ok(())
     }
 
+    /// Refuses to touch a database that was migrated by a newer build, since
+    /// this binary would not understand the tables it expects to find there.
+    async fn check_schema_version(&self) -> Result<()> {
+        let latest_known = MIGRATOR.iter().map(|m| m.version).max().unwrap_or_default();
+
+        match self.schema_version().await? {
+            Some(applied) if applied > latest_known => Err(AppError::Internal(anyhow::anyhow!(
+                "Database schema version {} is newer than the latest version {} known to this build; \
+                 upgrade rcpadmin-backend before starting it against this database",
+                applied,
+                latest_known
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Highest successfully applied migration version, or `None` for a fresh database.
+    async fn schema_version(&self) -> Result<Option<i64>> {
+        let has_migrations_table: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if !has_migrations_table {
+            return Ok(None);
+        }
+
+        let version =
+            sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(version)
+    }
+
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
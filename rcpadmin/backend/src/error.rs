@@ -1,31 +1,154 @@
 use axum::{
-    http::StatusCode,
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
 use thiserror::Error;
+use tracing::error;
+use utoipa::{ToResponse, ToSchema};
+
+use crate::request_id;
+
+/// Stable, machine-readable error codes, sent as `code` in every error
+/// response. Clients should branch on these rather than on `error`, whose
+/// wording may change. Codes are never renamed or reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// No bearer token was sent (401)
+    AuthRequired,
+    /// The token or refresh token is malformed or not recognised (401)
+    AuthTokenInvalid,
+    /// The token has expired; refresh it or log in again (401)
+    AuthTokenExpired,
+    /// The token was revoked by a logout or an administrator (401)
+    AuthTokenRevoked,
+    /// Wrong username or password (401)
+    AuthInvalidCredentials,
+    /// The account is disabled or was deleted (401)
+    AuthAccountDisabled,
+    /// Too many failed logins from this address or for this username; wait
+    /// for the `Retry-After` header's seconds or ask an admin to unlock (429)
+    AuthTooManyAttempts,
+    /// The two-factor code or recovery code was wrong or already used (401)
+    AuthOtpInvalid,
+    /// The role requires two-factor authentication; enroll via
+    /// `/auth/totp/enroll` before using the rest of the API (403)
+    AuthTotpEnrollmentRequired,
+    /// Authenticated, but lacking the permission the route requires (403)
+    PermissionDenied,
+    /// The input was rejected; `details` lists per-field problems when known (400)
+    ValidationFailed,
+    /// The body or query string could not be parsed (400)
+    MalformedRequest,
+    /// The resource does not exist (404)
+    NotFound,
+    /// The change conflicts with existing data, e.g. a duplicate name (409)
+    Conflict,
+    /// The RCP daemon could not be reached or timed out (502)
+    DaemonUnreachable,
+    /// The RCP daemon answered with an error or an unexpected response (502)
+    DaemonError,
+    /// An upstream service other than the daemon failed (502)
+    ExternalServiceError,
+    /// The admin database failed; the cause is logged under the request id (500)
+    DatabaseError,
+    /// Any other server-side failure; the cause is logged under the request id (500)
+    InternalError,
+}
+
+/// A validation failure tied to one input field.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema, ToResponse)]
+#[response(description = "Error")]
+pub struct ErrorBody {
+    pub error: String,
+    pub code: ErrorCode,
+    pub status: u16,
+    /// Also sent as the `X-Request-Id` response header
+    pub request_id: Option<String>,
+    /// Per-field problems, for `VALIDATION_FAILED`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldError>>,
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
+    #[error("Authentication error: Missing authorization header")]
+    MissingToken,
+
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    #[error("Authentication error: {0}")]
+    TokenExpired(String),
+
+    #[error("Authentication error: {0}")]
+    TokenRevoked(String),
+
+    #[error("Authentication error: Invalid credentials")]
+    InvalidCredentials,
+
+    #[error("Authentication error: {0}")]
+    AccountDisabled(String),
+
+    /// Seconds until the client may try again, sent as `Retry-After`
+    #[error("Authentication error: Too many failed login attempts, retry in {0}s")]
+    TooManyAttempts(i64),
+
+    #[error("Authentication error: Invalid two-factor code")]
+    InvalidOtp,
+
+    #[error("Authorization error: Two-factor authentication must be enabled for this account")]
+    TotpEnrollmentRequired,
+
     #[error("Authorization error: {0}")]
     Unauthorized(String),
 
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Validation errors reported per field, listed under `details` in the body
+    #[error("Validation error: {} invalid field(s)", .0.len())]
+    InvalidFields(Vec<FieldError>),
+
+    #[error("Malformed request: {0}")]
+    MalformedRequest(String),
+
+    #[error("RCP daemon unreachable: {0}")]
+    DaemonUnreachable(String),
+
     #[error("RCP daemon error: {0}")]
     RcpDaemon(String),
 
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("External service error: {0}")]
     External(String),
 
@@ -39,38 +162,141 @@ pub enum AppError {
     Http(#[from] reqwest::Error),
 }
 
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::MissingToken => ErrorCode::AuthRequired,
+            AppError::Auth(_) => ErrorCode::AuthTokenInvalid,
+            AppError::TokenExpired(_) => ErrorCode::AuthTokenExpired,
+            AppError::TokenRevoked(_) => ErrorCode::AuthTokenRevoked,
+            AppError::InvalidCredentials => ErrorCode::AuthInvalidCredentials,
+            AppError::AccountDisabled(_) => ErrorCode::AuthAccountDisabled,
+            AppError::TooManyAttempts(_) => ErrorCode::AuthTooManyAttempts,
+            AppError::InvalidOtp => ErrorCode::AuthOtpInvalid,
+            AppError::TotpEnrollmentRequired => ErrorCode::AuthTotpEnrollmentRequired,
+            AppError::Unauthorized(_) => ErrorCode::PermissionDenied,
+            AppError::Validation(_) | AppError::InvalidFields(_) => ErrorCode::ValidationFailed,
+            AppError::MalformedRequest(_) => ErrorCode::MalformedRequest,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::DaemonUnreachable(_) => ErrorCode::DaemonUnreachable,
+            AppError::RcpDaemon(_) => ErrorCode::DaemonError,
+            AppError::External(_) => ErrorCode::ExternalServiceError,
+            AppError::Database(_) => ErrorCode::DatabaseError,
+            AppError::Internal(_) | AppError::Json(_) | AppError::Http(_) => {
+                ErrorCode::InternalError
+            }
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self.code() {
+            ErrorCode::AuthRequired
+            | ErrorCode::AuthTokenInvalid
+            | ErrorCode::AuthTokenExpired
+            | ErrorCode::AuthTokenRevoked
+            | ErrorCode::AuthInvalidCredentials
+            | ErrorCode::AuthAccountDisabled
+            | ErrorCode::AuthOtpInvalid => StatusCode::UNAUTHORIZED,
+            ErrorCode::AuthTooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::AuthTotpEnrollmentRequired | ErrorCode::PermissionDenied => {
+                StatusCode::FORBIDDEN
+            }
+            ErrorCode::ValidationFailed | ErrorCode::MalformedRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::DaemonUnreachable
+            | ErrorCode::DaemonError
+            | ErrorCode::ExternalServiceError => StatusCode::BAD_GATEWAY,
+            ErrorCode::DatabaseError | ErrorCode::InternalError => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// The JSON error body; `status` may differ from [`AppError::status`]
+    /// when re-rendering a framework rejection.
+    fn render(self, status: StatusCode) -> Response {
+        // Server-side causes stay in the log, which carries the request id
+        let error_message = match &self {
+            AppError::Database(_) => "Database error".to_string(),
+            AppError::External(_) => "External service error".to_string(),
+            AppError::Internal(_) | AppError::Json(_) | AppError::Http(_) => {
+                "Internal server error".to_string()
+            }
+            _ => self.to_string(),
+        };
+        if status.is_server_error() {
+            error!("{}", self);
+        }
+
+        let code = self.code();
+        let retry_after = match &self {
+            AppError::TooManyAttempts(secs) => Some(*secs),
+            _ => None,
+        };
+        let details = match self {
+            AppError::InvalidFields(fields) => Some(fields),
+            _ => None,
+        };
+        let body = ErrorBody {
+            error: error_message,
+            code,
+            status: status.as_u16(),
+            request_id: request_id::current(),
+            details,
+        };
+
+        let mut response = (status, Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.max(1).into());
+        }
+        response
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Auth(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AppError::Unauthorized(_) => (StatusCode::FORBIDDEN, self.to_string()),
-            AppError::Validation(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            AppError::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
-            AppError::Database(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
-            ),
-            AppError::RcpDaemon(_) => (
-                StatusCode::BAD_GATEWAY,
-                "RCP daemon unavailable".to_string(),
-            ),
-            AppError::External(_) => (
-                StatusCode::BAD_GATEWAY,
-                "External service error".to_string(),
-            ),
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal server error".to_string(),
-            ),
-        };
+        let status = self.status();
+        self.render(status)
+    }
+}
+
+/// Largest extractor rejection message that gets rewritten
+const MAX_REJECTION_BODY: usize = 16 * 1024;
 
-        let body = Json(json!({
-            "error": error_message,
-            "status": status.as_u16()
-        }));
+/// Rewrites axum's plain-text extractor rejections (bad JSON, bad query
+/// strings) into the standard error body, so every 4xx carries a `code`.
+pub async fn normalize_rejections(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
 
-        (status, body).into_response()
+    let status = response.status();
+    let is_text = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/plain"));
+    let is_rejection = matches!(
+        status,
+        StatusCode::BAD_REQUEST
+            | StatusCode::UNPROCESSABLE_ENTITY
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    if !is_text || !is_rejection {
+        return response;
     }
+
+    let (parts, body) = response.into_parts();
+    let message = match to_bytes(body, MAX_REJECTION_BODY).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    // Keep the original status (415 for a missing content type, 422 for a
+    // body that parsed but did not fit the model)
+    AppError::MalformedRequest(message).render(status)
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
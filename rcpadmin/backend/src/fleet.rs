@@ -0,0 +1,309 @@
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures_util::future::join_all;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{AppError, Result},
+    models::{DaemonDb, DaemonHealth},
+    services::{
+        daemon::{validate_url, DaemonService},
+        rcpdaemon::{DaemonClientOptions, RcpDaemonClient},
+    },
+    AppState,
+};
+
+/// A registered daemon and the client used to reach it.
+pub struct DaemonHandle {
+    pub id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    pub client: RcpDaemonClient,
+}
+
+/// Clients for every registered daemon, kept in step with the `daemons`
+/// table by the daemons API.
+pub struct DaemonRegistry {
+    options: DaemonClientOptions,
+    daemons: RwLock<HashMap<Uuid, Arc<DaemonHandle>>>,
+}
+
+impl DaemonRegistry {
+    /// `options` apply to every daemon, except for the token each one has.
+    /// Daemons whose row has no usable URL are left out, so their routes
+    /// answer 404 until the URL is fixed through the API.
+    pub async fn load(db: &Database, options: DaemonClientOptions) -> Result<Self> {
+        let registry = Self {
+            options,
+            daemons: RwLock::new(HashMap::new()),
+        };
+        for daemon in DaemonService::new(db.clone()).list().await? {
+            if let Err(e) = registry.upsert(&daemon) {
+                error!("Not loading RCP daemon '{}': {}", daemon.name, e);
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<Arc<DaemonHandle>> {
+        self.daemons
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound("Daemon not found".to_string()))
+    }
+
+    /// The daemons that are polled and reachable through the API, by name.
+    pub fn enabled(&self) -> Vec<Arc<DaemonHandle>> {
+        let mut daemons: Vec<_> = self
+            .daemons
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|d| d.enabled)
+            .cloned()
+            .collect();
+        daemons.sort_by(|a, b| a.name.cmp(&b.name));
+        daemons
+    }
+
+    /// Replaces the client of `daemon` with one for its current settings.
+    /// Requests already running finish on the old one.
+    pub fn upsert(&self, daemon: &DaemonDb) -> Result<Arc<DaemonHandle>> {
+        let id = Uuid::parse_str(&daemon.id)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid daemon id: {}", e)))?;
+        let url = validate_url(&daemon.url)?;
+        let handle = Arc::new(DaemonHandle {
+            id,
+            name: daemon.name.clone(),
+            enabled: daemon.enabled,
+            client: RcpDaemonClient::new(url, self.options.clone())?
+                .with_auth_token(daemon.token.clone()),
+        });
+
+        self.daemons
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, handle.clone());
+        Ok(handle)
+    }
+
+    pub fn remove(&self, id: Uuid) {
+        self.daemons
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+    }
+}
+
+/// Route layer for routes nested under `/daemons/:daemon_id`: looks up the
+/// daemon and inserts its [`DaemonHandle`] into the request extensions.
+/// Disabled daemons are refused.
+pub async fn resolve_daemon(
+    State(state): State<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let daemon = params
+        .get("daemon_id")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| AppError::NotFound("Daemon not found".to_string()))
+        .and_then(|id| state.daemons.get(id));
+
+    match daemon {
+        Ok(daemon) if !daemon.enabled => {
+            AppError::Conflict(format!("Daemon '{}' is disabled", daemon.name)).into_response()
+        }
+        Ok(daemon) => {
+            request.extensions_mut().insert(daemon);
+            next.run(request).await
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Checks every enabled daemon each `health_interval_secs` and records the
+/// outcome on its `daemons` row.
+pub fn spawn_health_poller(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            state.config.rcpdaemon.health_interval_secs.max(1),
+        ));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let daemons = state.daemons.enabled();
+            join_all(daemons.iter().map(|daemon| check_health(&state, daemon))).await;
+        }
+    });
+}
+
+/// Asks the daemon for its status and records whether it answered. Changes
+/// in health are logged.
+pub async fn check_health(state: &AppState, daemon: &DaemonHandle) {
+    let (health, error) = match daemon.client.get_status().await {
+        Ok(_) => (DaemonHealth::Healthy, None),
+        Err(AppError::DaemonUnreachable(e)) => (DaemonHealth::Unreachable, Some(e)),
+        Err(e) => (DaemonHealth::Error, Some(e.to_string())),
+    };
+
+    let previous = DaemonService::new(state.db.clone())
+        .record_health(daemon.id, health, error.as_deref(), Utc::now())
+        .await;
+
+    match previous {
+        Ok(Some(previous)) if previous != health => match &error {
+            None => info!("RCP daemon '{}' is healthy", daemon.name),
+            Some(e) => warn!("RCP daemon '{}' is {}: {}", daemon.name, health.as_str(), e),
+        },
+        Ok(_) => {}
+        Err(e) => error!(
+            "Failed to record health of RCP daemon '{}': {}",
+            daemon.name, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::DaemonConfig, models::CreateDaemon, services::daemon::DEFAULT_DAEMON_ID};
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::from_fn_with_state,
+        routing::get,
+        Extension, Router,
+    };
+    use tower::ServiceExt;
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn leaves_out_daemons_without_a_url() {
+        let db = database().await;
+
+        // As seeded by the migration, before `init_default` filled it in
+        let registry = DaemonRegistry::load(&db, DaemonClientOptions::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            registry.get(DEFAULT_DAEMON_ID),
+            Err(AppError::NotFound(_))
+        ));
+
+        DaemonService::new(db.clone())
+            .init_default(&DaemonConfig::default())
+            .await
+            .unwrap();
+        let registry = DaemonRegistry::load(&db, DaemonClientOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(registry.get(DEFAULT_DAEMON_ID).unwrap().name, "default");
+        assert_eq!(registry.enabled().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_clients_in_step_with_the_rows() {
+        let db = database().await;
+        let registry = DaemonRegistry::load(&db, DaemonClientOptions::default())
+            .await
+            .unwrap();
+        let mut daemon = DaemonService::new(db.clone())
+            .create(&CreateDaemon {
+                name: "edge".to_string(),
+                url: "http://10.0.0.5:3030".to_string(),
+                token: None,
+                enabled: Some(false),
+            })
+            .await
+            .unwrap();
+
+        let handle = registry.upsert(&daemon).unwrap();
+        assert!(!handle.enabled);
+        assert!(registry.enabled().is_empty());
+
+        daemon.url = "ftp://10.0.0.5".to_string();
+        assert!(matches!(
+            registry.upsert(&daemon),
+            Err(AppError::Validation(_))
+        ));
+
+        registry.remove(handle.id);
+        assert!(matches!(
+            registry.get(handle.id),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    async fn resolve(state: &AppState, daemon_id: &str) -> (StatusCode, String) {
+        let router =
+            Router::new()
+                .route(
+                    "/daemons/:daemon_id/name",
+                    get(
+                        |Extension(daemon): Extension<Arc<DaemonHandle>>| async move {
+                            daemon.name.clone()
+                        },
+                    ),
+                )
+                .route_layer(from_fn_with_state(state.clone(), resolve_daemon))
+                .with_state(state.clone());
+        let request = Request::get(format!("/daemons/{}/name", daemon_id))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn resolves_enabled_daemons_only() {
+        let state = crate::test_state().await;
+
+        assert_eq!(
+            resolve(&state, &DEFAULT_DAEMON_ID.to_string()).await,
+            (StatusCode::OK, "default".to_string())
+        );
+        assert_eq!(
+            resolve(&state, &Uuid::new_v4().to_string()).await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(resolve(&state, "not-a-uuid").await.0, StatusCode::NOT_FOUND);
+
+        let disabled = DaemonService::new(state.db.clone())
+            .create(&CreateDaemon {
+                name: "edge".to_string(),
+                url: "http://10.0.0.5:3030".to_string(),
+                token: None,
+                enabled: Some(false),
+            })
+            .await
+            .unwrap();
+        state.daemons.upsert(&disabled).unwrap();
+        assert_eq!(resolve(&state, &disabled.id).await.0, StatusCode::CONFLICT);
+    }
+}
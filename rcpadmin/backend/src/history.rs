@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+use crate::{fleet::DaemonHandle, services::metrics_history::MetricsHistoryService, AppState};
+
+/// How often complete buckets are rolled up and expired samples deleted
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Samples system and session metrics from every enabled daemon into the
+/// history tables, and periodically downsamples and expires them. Unlike the
+/// WebSocket poller this runs whether or not anyone is watching.
+pub fn spawn_metrics_recorder(state: AppState) {
+    tokio::spawn(async move {
+        let sample_interval = Duration::from_secs(state.config.metrics.sample_interval_secs.max(1));
+        let mut samples = tokio::time::interval(sample_interval);
+        samples.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut maintenance = tokio::time::interval(MAINTENANCE_INTERVAL);
+        maintenance.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        let history = MetricsHistoryService::new(state.db.clone());
+
+        loop {
+            tokio::select! {
+                _ = samples.tick() => {
+                    let now = Utc::now();
+                    let daemons = state.daemons.enabled();
+                    join_all(daemons.iter().map(|daemon| sample(&history, daemon, now))).await;
+                }
+                _ = maintenance.tick() => {
+                    let now = Utc::now();
+
+                    if let Err(e) = history.downsample(now).await {
+                        warn!("Failed to downsample metrics history: {}", e);
+                    }
+
+                    match history.purge(now, state.config.metrics.retention_days).await {
+                        Ok(0) => {}
+                        Ok(removed) => info!("Expired {} metrics samples", removed),
+                        Err(e) => warn!("Failed to expire metrics history: {}", e),
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn sample(history: &MetricsHistoryService, daemon: &DaemonHandle, now: DateTime<Utc>) {
+    let (system, sessions) = tokio::join!(
+        daemon.client.get_system_metrics(),
+        daemon.client.get_sessions()
+    );
+
+    let (system, sessions) = match (system, sessions) {
+        (Ok(system), Ok(sessions)) => (system, sessions),
+        (Err(e), _) | (_, Err(e)) => {
+            debug!(
+                "Skipping metrics sample of RCP daemon '{}': {}",
+                daemon.name, e
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = history.record(daemon.id, now, &system, &sessions).await {
+        error!(
+            "Failed to record metrics sample of RCP daemon '{}': {}",
+            daemon.name, e
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast;
+use tracing::{
+    debug,
+    field::{Field, Visit},
+    info, Event, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{LogEntry, LogLevel, LogList, LogQuery, LogSource},
+    services::rcpdaemon::api::DaemonLogEntry,
+    AppState,
+};
+
+/// Entries kept in memory, across the backend and the daemons
+const CAPACITY: usize = 10_000;
+const CHANNEL_SIZE: usize = 1024;
+/// Entries returned by a query that gives no `limit`
+const DEFAULT_LIMIT: usize = 500;
+/// How often each daemon is asked for new log lines
+const DAEMON_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bounded ring buffer of recent log events, with a live feed for tails.
+pub struct LogBuffer {
+    sender: broadcast::Sender<LogEntry>,
+    inner: Mutex<BufferInner>,
+}
+
+struct BufferInner {
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+impl LogBuffer {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_SIZE).0,
+            inner: Mutex::new(BufferInner {
+                entries: VecDeque::with_capacity(CAPACITY),
+                next_id: 1,
+            }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.sender.subscribe()
+    }
+
+    pub fn push(
+        &self,
+        timestamp: DateTime<Utc>,
+        source: LogSource,
+        level: LogLevel,
+        target: String,
+        message: String,
+        fields: Map<String, Value>,
+    ) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        let entry = LogEntry {
+            id: inner.next_id,
+            timestamp,
+            source,
+            level,
+            target,
+            message,
+            fields,
+        };
+        inner.next_id += 1;
+
+        if inner.entries.len() == CAPACITY {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(entry.clone());
+
+        // Sent under the lock so tails see entries in id order
+        let _ = self.sender.send(entry);
+    }
+
+    /// The newest entries matching `query`, oldest first.
+    pub fn query(&self, query: &LogQuery) -> LogList {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(CAPACITY);
+
+        let matching: Vec<&LogEntry> = inner.entries.iter().filter(|e| query.matches(e)).collect();
+        let matched = matching.len();
+        let entries = matching
+            .into_iter()
+            .skip(matched.saturating_sub(limit))
+            .cloned()
+            .collect();
+
+        LogList { entries, matched }
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `tracing` layer that copies the backend's own events into a [`LogBuffer`].
+pub struct LogCapture {
+    buffer: Arc<LogBuffer>,
+}
+
+impl LogCapture {
+    pub fn new(buffer: Arc<LogBuffer>) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        self.buffer.push(
+            Utc::now(),
+            LogSource::Backend,
+            metadata.level().into(),
+            metadata.target().to_string(),
+            visitor.message,
+            visitor.fields,
+        );
+    }
+}
+
+/// Splits an event into its `message` and its remaining fields.
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn record_value(&mut self, field: &Field, value: Value) {
+        match (field.name(), value) {
+            ("message", Value::String(message)) => self.message = message,
+            (name, value) => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_value(field, Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_value(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, value.into());
+    }
+}
+
+/// Pulls new log lines from every enabled daemon into the shared buffer, so
+/// their errors (e.g. a failed application launch) show up next to ours.
+pub fn spawn_daemon_log_collector(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DAEMON_POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut since: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+        let mut unsupported_reported: HashSet<Uuid> = HashSet::new();
+
+        loop {
+            interval.tick().await;
+
+            let daemons = state.daemons.enabled();
+            since.retain(|id, _| daemons.iter().any(|d| d.id == *id));
+            let polls = join_all(
+                daemons
+                    .iter()
+                    .map(|daemon| daemon.client.get_logs(since.get(&daemon.id).copied())),
+            )
+            .await;
+
+            for (daemon, entries) in daemons.iter().zip(polls) {
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(AppError::NotFound(_)) => {
+                        // Older daemons have no logs endpoint; keep trying in
+                        // case it is upgraded, but only say so once
+                        if unsupported_reported.insert(daemon.id) {
+                            info!(
+                                "RCP daemon '{}' does not serve logs; only backend logs are available for it",
+                                daemon.name
+                            );
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        debug!("Failed to poll logs of RCP daemon '{}': {}", daemon.name, e);
+                        continue;
+                    }
+                };
+
+                let last = collect(
+                    &state.logs,
+                    &daemon.name,
+                    entries,
+                    since.get(&daemon.id).copied(),
+                );
+                if let Some(last) = last {
+                    since.insert(daemon.id, last);
+                }
+            }
+        }
+    });
+}
+
+/// Adds the lines of daemon `daemon` newer than `since`, each with a
+/// `daemon` field holding its name. Returns the newest timestamp added.
+fn collect(
+    logs: &LogBuffer,
+    daemon: &str,
+    entries: Vec<DaemonLogEntry>,
+    mut since: Option<DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let mut last = None;
+    for mut entry in entries {
+        // The daemon may repeat lines logged in the same millisecond as `since`
+        if since.is_some_and(|since| entry.timestamp <= since) {
+            continue;
+        }
+        since = Some(entry.timestamp);
+        last = since;
+
+        entry
+            .fields
+            .insert("daemon".to_string(), Value::String(daemon.to_string()));
+        logs.push(
+            entry.timestamp,
+            LogSource::Daemon,
+            entry.level.parse().unwrap_or(LogLevel::Info),
+            entry.target.unwrap_or_else(|| "rcpdaemon".to_string()),
+            entry.message,
+            entry.fields,
+        );
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        "2026-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + chrono::Duration::seconds(secs)
+    }
+
+    fn line(secs: i64, level: &str, message: &str) -> DaemonLogEntry {
+        DaemonLogEntry {
+            timestamp: at(secs),
+            level: level.to_string(),
+            target: None,
+            message: message.to_string(),
+            fields: Map::new(),
+        }
+    }
+
+    #[test]
+    fn collects_daemon_lines_next_to_backend_events() {
+        let logs = LogBuffer::new();
+        logs.push(
+            Utc::now(),
+            LogSource::Backend,
+            LogLevel::Info,
+            "rcpadmin_backend::api".to_string(),
+            "Launching calc".to_string(),
+            Map::new(),
+        );
+
+        let last = collect(
+            &logs,
+            "edge-1",
+            vec![
+                line(1, "ERROR", "Failed to launch calc: exec not found"),
+                line(2, "verbose", "Cleaning up"),
+            ],
+            None,
+        );
+        assert_eq!(last, Some(at(2)));
+
+        let daemon = logs.query(&LogQuery {
+            source: Some(LogSource::Daemon),
+            ..Default::default()
+        });
+        assert_eq!(daemon.matched, 2);
+        let failed = &daemon.entries[0];
+        assert_eq!(failed.level, LogLevel::Error);
+        assert_eq!(failed.target, "rcpdaemon");
+        assert_eq!(failed.fields["daemon"], "edge-1");
+        // Unknown levels are kept as info
+        assert_eq!(daemon.entries[1].level, LogLevel::Info);
+
+        let errors = logs.query(&LogQuery {
+            level: Some(LogLevel::Error),
+            daemon: Some("edge-1".to_string()),
+            q: Some("calc".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(errors.matched, 1);
+        assert_eq!(logs.query(&LogQuery::default()).matched, 3);
+    }
+
+    #[test]
+    fn skips_lines_already_collected() {
+        let logs = LogBuffer::new();
+        let since = collect(&logs, "edge-1", vec![line(1, "info", "first")], None);
+
+        // The next poll repeats the line at `since`
+        let last = collect(
+            &logs,
+            "edge-1",
+            vec![line(1, "info", "first"), line(3, "info", "second")],
+            since,
+        );
+        assert_eq!(last, Some(at(3)));
+        assert_eq!(
+            collect(&logs, "edge-1", vec![line(3, "info", "second")], last),
+            None
+        );
+
+        let messages: Vec<String> = logs
+            .query(&LogQuery::default())
+            .entries
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(messages, ["first", "second"]);
+    }
+}
@@ -1,22 +1,35 @@
-use axum::{middleware, response::Json, routing::get, Router};
-use std::sync::Arc;
+use axum::{extract::Request, middleware, response::Json, routing::get, Router};
+use std::{net::SocketAddr, sync::Arc};
 use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing::info;
+use tower_http::trace::TraceLayer;
+use tracing::{info, info_span};
+use tracing_subscriber::{
+    filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
 
 mod api;
+mod audit;
 mod auth;
 mod config;
+mod cors;
 mod db;
 mod error;
+mod fleet;
+mod history;
+mod logs;
 mod models;
+mod request_id;
 mod services;
+mod telemetry;
+mod tls;
 mod websocket;
 
 use crate::{
-    auth::{protect, require_admin},
+    auth::protect_ws,
     config::Config,
     db::Database,
+    fleet::{resolve_daemon, DaemonRegistry},
+    services::{daemon::DaemonService, rcpdaemon::DaemonClientOptions},
 };
 
 pub type AppState = Arc<AppStateInner>;
@@ -24,6 +37,46 @@ pub type AppState = Arc<AppStateInner>;
 pub struct AppStateInner {
     pub db: Database,
     pub config: Config,
+    pub daemons: DaemonRegistry,
+    pub metrics: websocket::MetricsHub,
+    pub session_events: websocket::SessionEventHub,
+    pub telemetry: telemetry::Telemetry,
+    pub logs: Arc<logs::LogBuffer>,
+    pub audit_limit: audit::AnonymousEventLimit,
+}
+
+/// State over a migrated in-memory database, for tests that go through the
+/// middleware or the router.
+#[cfg(test)]
+pub async fn test_state() -> AppState {
+    let db = Database::new("sqlite::memory:").await.unwrap();
+    db.migrate().await.unwrap();
+    let config = Config {
+        auth: config::AuthConfig {
+            jwt_secret: "test-secret".to_string(),
+            ..Default::default()
+        },
+        static_dir: None,
+        ..Config::default()
+    };
+    DaemonService::new(db.clone())
+        .init_default(&config.rcpdaemon)
+        .await
+        .unwrap();
+    let daemons = DaemonRegistry::load(&db, DaemonClientOptions::from(&config))
+        .await
+        .unwrap();
+
+    Arc::new(AppStateInner {
+        audit_limit: audit::AnonymousEventLimit::new(config.audit.anonymous_events_per_minute),
+        db,
+        config,
+        daemons,
+        metrics: websocket::MetricsHub::new(),
+        session_events: websocket::SessionEventHub::new(),
+        telemetry: telemetry::Telemetry::new(),
+        logs: Arc::new(logs::LogBuffer::new()),
+    })
 }
 
 #[tokio::main]
@@ -31,76 +84,151 @@ async fn main() -> anyhow::Result<()> {
     // Load .env file if it exists
     dotenvy::dotenv().ok();
 
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter("rcpadmin_backend=debug,tower_http=debug")
+    // Initialize tracing; INFO and above is also kept in memory for the logs API
+    let log_buffer = Arc::new(logs::LogBuffer::new());
+    tracing_subscriber::registry()
+        .with(EnvFilter::new("rcpadmin_backend=debug,tower_http=debug"))
+        .with(tracing_subscriber::fmt::layer())
+        .with(logs::LogCapture::new(log_buffer.clone()).with_filter(LevelFilter::INFO))
         .init();
 
     info!("Starting RCP Admin Backend v{}", env!("CARGO_PKG_VERSION"));
 
     // Load configuration
-    let config = Config::from_env()?;
+    let config = Config::load()?;
     info!("Configuration loaded");
 
     // Initialize database
-    // let db = Database::new(&config.database_url).await?;
-    // db.migrate().await?;  // Temporarily disable migrations
-    info!("Database initialization skipped for now");
-
-    // Create a dummy database placeholder
-    let db = Database::new("sqlite::memory:").await.unwrap_or_else(|_| {
-        // If even memory database fails, we'll handle it gracefully
-        panic!("Could not create database connection")
-    });
+    let db = Database::new(&config.database_url).await?;
+    db.migrate().await?;
+    info!("Database ready at {}", config.database_url);
+
+    // Deploy pipelines run migrations as a separate step before rolling out
+    if std::env::args().skip(1).any(|arg| arg == "--migrate-only") {
+        info!("Migrations applied, exiting (--migrate-only)");
+        return Ok(());
+    }
+
+    // Clients for the registered RCP daemons; unreachable ones are not
+    // fatal, the health poller reports them
+    DaemonService::new(db.clone())
+        .init_default(&config.rcpdaemon)
+        .await?;
+    let daemons = DaemonRegistry::load(&db, DaemonClientOptions::from(&config)).await?;
 
     // Create application state
     let state = Arc::new(AppStateInner {
         db,
         config: config.clone(),
+        daemons,
+        metrics: websocket::MetricsHub::new(),
+        session_events: websocket::SessionEventHub::new(),
+        telemetry: telemetry::Telemetry::new(),
+        logs: log_buffer,
+        audit_limit: audit::AnonymousEventLimit::new(config.audit.anonymous_events_per_minute),
     });
 
+    // Background tasks
+    fleet::spawn_health_poller(state.clone());
+    websocket::spawn_metrics_poller(state.clone());
+    websocket::spawn_session_watcher(state.clone());
+    history::spawn_metrics_recorder(state.clone());
+    logs::spawn_daemon_log_collector(state.clone());
+    audit::spawn_audit_purger(state.clone());
+
     // Build application router
     let app = create_router(state);
 
     // Start server
     let listener = TcpListener::bind(&config.bind_address).await?;
-    info!("Server listening on {}", config.bind_address);
-
-    axum::serve(listener, app).await?;
+    if config.tls.enabled {
+        let settings = tls::TlsSettings::load(&config.tls)?;
+        #[cfg(unix)]
+        tls::spawn_reload_on_sighup(settings.clone())?;
+        info!(
+            "Server listening on https://{}{}",
+            config.bind_address,
+            if config.tls.client_ca_path.is_some() {
+                " (client certificates required)"
+            } else {
+                ""
+            }
+        );
+        tls::serve(listener, settings, app).await?;
+    } else {
+        info!("Server listening on {}", config.bind_address);
+        // Connection info gives the audit log the caller's address
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
+    }
 
     Ok(())
 }
 
 fn create_router(state: AppState) -> Router {
-    // Create protected API routes
-    let protected_api = Router::new()
-        .nest("/server", api::server::create_routes())
-        .nest("/applications", api::applications::create_routes())
-        .nest("/sessions", api::sessions::create_routes())
-        .nest("/system", api::system::create_routes())
-        .layer(middleware::from_fn_with_state(state.clone(), protect));
-
-    // Create admin-only API routes
-    let admin_api = Router::new()
-        .nest("/users", api::users::create_routes())
-        .layer(middleware::from_fn_with_state(state.clone(), require_admin));
-
     // Build the main router
-    Router::new()
+    let mut router = Router::new()
         // Health check
         .route("/health", get(health_check))
-        // Auth routes (no middleware)
-        .nest("/api/v1/auth", api::auth::create_routes())
-        // Protected API routes
-        .nest("/api/v1", protected_api)
-        .nest("/api/v1", admin_api)
+        // Prometheus scrape endpoint
+        .merge(telemetry::create_routes())
+        // REST API
+        .nest("/api/v1", api::create_routes(&state))
         // WebSocket routes
-        .nest("/ws", websocket::create_routes())
-        // Static files (for serving frontend in production)
-        .fallback_service(tower_http::services::ServeDir::new("../web/dist"))
+        .nest(
+            "/ws",
+            websocket::create_routes()
+                .nest(
+                    "/daemons/:daemon_id",
+                    websocket::create_daemon_routes().route_layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        resolve_daemon,
+                    )),
+                )
+                .layer(middleware::from_fn_with_state(state.clone(), protect_ws)),
+        )
+        // Lets the audit log skip requests for unknown paths
+        .route_layer(middleware::from_fn(audit::mark_routed));
+
+    // Static files (for serving frontend in production)
+    if let Some(static_dir) = &state.config.static_dir {
+        router = router.fallback_service(tower_http::services::ServeDir::new(static_dir));
+    }
+
+    router
         // Global middleware
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(error::normalize_rejections))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit::record_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(cors::CorsPolicy::new(&state.config.cors)),
+            cors::apply_cors,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            telemetry::track_requests,
+        ))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &Request| {
+                let request_id = request
+                    .headers()
+                    .get(request_id::REQUEST_ID_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default();
+                info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %auth::redact_access_token(request.uri()),
+                    request_id,
+                )
+            }),
+        )
+        .layer(middleware::from_fn(request_id::assign_request_id))
         .with_state(state)
 }
 
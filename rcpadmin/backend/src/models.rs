@@ -3,8 +3,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::fmt;
 use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::error::FieldError;
+
 // Database models that match SQLite schema exactly
 #[derive(Debug, Clone, FromRow)]
 pub struct UserDb {
@@ -58,6 +61,14 @@ impl From<UserInfoDb> for UserInfo {
     }
 }
 
+#[derive(Debug, Clone, FromRow)]
+pub struct RefreshTokenDb {
+    pub id: String,
+    pub user_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
 // API models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -71,7 +82,7 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UserInfo {
     pub id: Uuid,
     pub username: String,
@@ -80,7 +91,19 @@ pub struct UserInfo {
     pub is_active: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl From<User> for UserInfo {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            is_active: user.is_active,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateUser {
     pub username: String,
     pub email: String,
@@ -89,7 +112,7 @@ pub struct CreateUser {
     pub is_active: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UpdateUser {
     pub username: Option<String>,
     pub email: Option<String>,
@@ -97,19 +120,125 @@ pub struct UpdateUser {
     pub is_active: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserListQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub role: Option<UserRole>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserList {
+    pub users: Vec<UserInfo>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
     pub user: UserInfo,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Returned by `login` instead of tokens when the account has two-factor
+/// authentication enabled; pass the challenge to `verify-otp`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OtpChallenge {
+    /// Always `true`; lets clients tell this apart from [`LoginResponse`]
+    pub otp_required: bool,
+    pub challenge_token: String,
+    /// Seconds the challenge stays valid
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Authenticated(LoginResponse),
+    OtpRequired(OtpChallenge),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VerifyOtpRequest {
+    pub challenge_token: String,
+    /// Current authenticator code, or one of the recovery codes
+    pub code: String,
+}
+
+/// A freshly generated TOTP secret, not active until confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpEnrollment {
+    /// Base32 secret, for authenticators that cannot scan the URI
+    pub secret: String,
+    /// `otpauth://totp/...` URI, usually shown as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpCodeRequest {
+    /// Current authenticator code (or, to disable, a recovery code)
+    pub code: String,
+}
+
+/// Shown once; only hashes are kept.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpRecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpStatus {
+    pub enabled: bool,
+    /// Whether the user's role requires two-factor authentication
+    pub required: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Issued by an admin and passed on to the user out of band.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PasswordResetToken {
+    /// Single use; only a hash is kept
+    pub reset_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResetPasswordRequest {
+    pub reset_token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+    /// Revoke every refresh token of the user, logging out all devices
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum UserRole {
     Admin,
     Operator,
@@ -139,9 +268,148 @@ impl FromStr for UserRole {
     }
 }
 
+/// A single capability checked by the API. Each role is granted a set of
+/// these through the `role_permissions` table.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+pub enum Permission {
+    #[serde(rename = "server:read")]
+    ServerRead,
+    #[serde(rename = "server:restart")]
+    ServerRestart,
+    #[serde(rename = "server:config")]
+    ServerConfig,
+    #[serde(rename = "apps:read")]
+    AppsRead,
+    #[serde(rename = "apps:manage")]
+    AppsManage,
+    #[serde(rename = "apps:launch")]
+    AppsLaunch,
+    #[serde(rename = "sessions:read")]
+    SessionsRead,
+    #[serde(rename = "sessions:terminate")]
+    SessionsTerminate,
+    #[serde(rename = "system:read")]
+    SystemRead,
+    #[serde(rename = "users:manage")]
+    UsersManage,
+    #[serde(rename = "roles:manage")]
+    RolesManage,
+    #[serde(rename = "audit:read")]
+    AuditRead,
+    #[serde(rename = "apikeys:manage")]
+    ApiKeysManage,
+    #[serde(rename = "daemons:manage")]
+    DaemonsManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 14] = [
+        Permission::ServerRead,
+        Permission::ServerRestart,
+        Permission::ServerConfig,
+        Permission::AppsRead,
+        Permission::AppsManage,
+        Permission::AppsLaunch,
+        Permission::SessionsRead,
+        Permission::SessionsTerminate,
+        Permission::SystemRead,
+        Permission::UsersManage,
+        Permission::RolesManage,
+        Permission::AuditRead,
+        Permission::ApiKeysManage,
+        Permission::DaemonsManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ServerRead => "server:read",
+            Permission::ServerRestart => "server:restart",
+            Permission::ServerConfig => "server:config",
+            Permission::AppsRead => "apps:read",
+            Permission::AppsManage => "apps:manage",
+            Permission::AppsLaunch => "apps:launch",
+            Permission::SessionsRead => "sessions:read",
+            Permission::SessionsTerminate => "sessions:terminate",
+            Permission::SystemRead => "system:read",
+            Permission::UsersManage => "users:manage",
+            Permission::RolesManage => "roles:manage",
+            Permission::AuditRead => "audit:read",
+            Permission::ApiKeysManage => "apikeys:manage",
+            Permission::DaemonsManage => "daemons:manage",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| format!("Unknown permission: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RolePermissions {
+    pub role: UserRole,
+    pub permissions: Vec<Permission>,
+    /// Members must enroll in two-factor authentication before using the API
+    pub require_totp: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRolePermissions {
+    pub permissions: Vec<Permission>,
+    /// Left unchanged when omitted
+    #[serde(default)]
+    pub require_totp: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub action: String,
+    pub resource: String,
+    pub outcome: String,
+    pub status_code: i64,
+    pub source_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub username: Option<String>,
+    pub outcome: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Export format, `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEventList {
+    pub events: Vec<AuditEvent>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
 // RCP Daemon Models
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerStatus {
     pub version: String,
     pub uptime: u64,
@@ -149,7 +417,7 @@ pub struct ServerStatus {
     pub system_metrics: SystemMetrics,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SystemMetrics {
     pub cpu_usage: f64,
     pub memory_usage: u64,
@@ -158,7 +426,7 @@ pub struct SystemMetrics {
     pub total_disk: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Session {
     pub id: Uuid,
     pub application_id: String,
@@ -169,7 +437,7 @@ pub struct Session {
     pub metrics: SessionMetrics,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum SessionStatus {
     Active,
     Idle,
@@ -177,7 +445,33 @@ pub enum SessionStatus {
     Terminated,
 }
 
+/// A change to a daemon session, pushed over `/ws/sessions`. Event ids
+/// increase monotonically across the fleet so a client can resume after
+/// reconnecting.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    /// The daemon the session runs on
+    pub daemon_id: Uuid,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+    pub session: Session,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// A session appeared on the daemon
+    Created,
+    /// The session moved from `previous` to its current status
+    StatusChanged { previous: SessionStatus },
+    /// The session is gone from the daemon; `session` is its last known
+    /// state with status `Terminated`
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SessionMetrics {
     pub cpu_usage: f64,
     pub memory_usage: u64,
@@ -185,7 +479,62 @@ pub struct SessionMetrics {
     pub network_tx: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Metrics History Models
+
+/// Time range for historical metrics. Without any field set, the metrics
+/// routes return the daemon's current values instead of a series.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MetricsRangeQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Bucket width in seconds
+    pub step: Option<i64>,
+}
+
+impl MetricsRangeQuery {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none() && self.step.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[aliases(
+    SystemMetricsSeries = MetricsSeries<SystemMetricsPoint>,
+    SessionMetricsSeries = MetricsSeries<SessionMetricsPoint>
+)]
+pub struct MetricsSeries<T> {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub step: i64,
+    pub points: Vec<T>,
+}
+
+/// System metrics averaged over one bucket of a [`MetricsSeries`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SystemMetricsPoint {
+    pub timestamp: DateTime<Utc>,
+    pub cpu_usage: f64,
+    pub memory_usage: u64,
+    pub total_memory: u64,
+    pub disk_usage: u64,
+    pub total_disk: u64,
+    pub network_rx: u64,
+    pub network_tx: u64,
+    pub active_sessions: f64,
+}
+
+/// Session metrics averaged over one bucket of a [`MetricsSeries`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionMetricsPoint {
+    pub timestamp: DateTime<Utc>,
+    pub cpu_usage: f64,
+    pub memory_usage: u64,
+    pub network_rx: u64,
+    pub network_tx: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Application {
     pub id: String,
     pub name: String,
@@ -200,7 +549,16 @@ pub struct Application {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An application as the daemon saved it, with the fields that could not be
+/// checked before it was sent.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SavedApplication {
+    #[serde(flatten)]
+    pub application: Application,
+    pub unverified: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateApplication {
     pub name: String,
     pub version: String,
@@ -210,3 +568,494 @@ pub struct CreateApplication {
     pub arguments: Vec<String>,
     pub environment: std::collections::HashMap<String, String>,
 }
+
+// Launch History Models
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchStatus {
+    /// The daemon started a session that has not ended yet
+    Running,
+    /// Stopped through the admin API
+    Stopped,
+    /// The session ended on its own
+    Exited,
+    /// The daemon refused or failed the launch
+    Failed,
+}
+
+impl LaunchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LaunchStatus::Running => "running",
+            LaunchStatus::Stopped => "stopped",
+            LaunchStatus::Exited => "exited",
+            LaunchStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for LaunchStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "running" => Ok(LaunchStatus::Running),
+            "stopped" => Ok(LaunchStatus::Stopped),
+            "exited" => Ok(LaunchStatus::Exited),
+            "failed" => Ok(LaunchStatus::Failed),
+            _ => Err(format!("Unknown launch status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct LaunchRecordDb {
+    pub id: i64,
+    pub application_id: String,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub arguments: String,
+    pub session_id: Option<String>,
+    pub status: String,
+    pub exit_code: Option<i64>,
+    pub error: Option<String>,
+    pub launched_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+impl From<LaunchRecordDb> for LaunchRecord {
+    fn from(db: LaunchRecordDb) -> Self {
+        Self {
+            id: db.id,
+            application_id: db.application_id,
+            user_id: db.user_id,
+            username: db.username,
+            arguments: serde_json::from_str(&db.arguments).unwrap_or_default(),
+            session_id: db.session_id.and_then(|id| Uuid::parse_str(&id).ok()),
+            status: LaunchStatus::from_str(&db.status).unwrap_or(LaunchStatus::Failed),
+            exit_code: db.exit_code,
+            error: db.error,
+            launched_at: db.launched_at,
+            ended_at: db.ended_at,
+            duration_ms: db.duration_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LaunchRecord {
+    pub id: i64,
+    pub application_id: String,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub arguments: Vec<String>,
+    pub session_id: Option<Uuid>,
+    pub status: LaunchStatus,
+    pub exit_code: Option<i64>,
+    pub error: Option<String>,
+    pub launched_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Body of `POST /applications/:id/launch`; without `arguments` the
+/// application's configured arguments are used.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct LaunchApplication {
+    pub arguments: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LaunchHistoryQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub status: Option<LaunchStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LaunchHistory {
+    pub launches: Vec<LaunchRecord>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+// Log Models
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<&tracing::Level> for LogLevel {
+    fn from(level: &tracing::Level) -> Self {
+        match *level {
+            tracing::Level::TRACE => LogLevel::Trace,
+            tracing::Level::DEBUG => LogLevel::Debug,
+            tracing::Level::INFO => LogLevel::Info,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::ERROR => LogLevel::Error,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(format!("Unknown log level: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    Backend,
+    Daemon,
+}
+
+/// One captured log event. Ids increase in arrival order.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogEntry {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub source: LogSource,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    #[schema(value_type = Object)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Filters shared by `/api/v1/system/logs` and the `/ws/logs` tail.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogQuery {
+    /// Minimum severity
+    pub level: Option<LogLevel>,
+    /// Matches the target and anything below it, e.g. `rcpadmin_backend::api`
+    pub target: Option<String>,
+    pub source: Option<LogSource>,
+    /// Name of the daemon that daemon entries came from
+    pub daemon: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Case-insensitive text searched for in the message and field values
+    pub q: Option<String>,
+    /// Maximum entries returned, newest kept
+    pub limit: Option<usize>,
+}
+
+impl LogQuery {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.level.is_some_and(|level| entry.level < level)
+            || self.source.is_some_and(|source| entry.source != source)
+            || self.from.is_some_and(|from| entry.timestamp < from)
+            || self.to.is_some_and(|to| entry.timestamp > to)
+        {
+            return false;
+        }
+
+        if let Some(daemon) = &self.daemon {
+            if entry.fields.get("daemon").and_then(|v| v.as_str()) != Some(daemon.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(target) = &self.target {
+            let below = entry
+                .target
+                .strip_prefix(target.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"));
+            if !below {
+                return false;
+            }
+        }
+
+        match &self.q {
+            Some(q) => {
+                let q = q.to_lowercase();
+                entry.message.to_lowercase().contains(&q)
+                    || entry
+                        .fields
+                        .values()
+                        .any(|value| value.to_string().to_lowercase().contains(&q))
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogList {
+    pub entries: Vec<LogEntry>,
+    /// Entries matching the filters before `limit` was applied
+    pub matched: usize,
+}
+
+// API keys
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiKeyDb {
+    pub id: String,
+    pub name: String,
+    pub key_prefix: String,
+    pub permissions: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyDb {
+    /// Permissions unknown to this build are dropped, as for roles.
+    pub fn permissions(&self) -> Vec<Permission> {
+        serde_json::from_str::<Vec<String>>(&self.permissions)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|p| Permission::from_str(p).ok())
+            .collect()
+    }
+}
+
+impl From<ApiKeyDb> for ApiKey {
+    fn from(db: ApiKeyDb) -> Self {
+        Self {
+            permissions: db.permissions(),
+            id: Uuid::parse_str(&db.id).unwrap_or_default(),
+            name: db.name,
+            prefix: db.key_prefix,
+            user_id: Uuid::parse_str(&db.user_id).unwrap_or_default(),
+            created_at: db.created_at,
+            expires_at: db.expires_at,
+            last_used_at: db.last_used_at,
+            revoked_at: db.revoked_at,
+        }
+    }
+}
+
+/// An API key as listed; the key itself is only shown when created.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// First characters of the key, to tell keys apart
+    pub prefix: String,
+    pub permissions: Vec<Permission>,
+    /// The user who created the key; it acts on their behalf
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Updated at most once a minute
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKey {
+    pub name: String,
+    /// Must be a subset of the creator's own permissions
+    pub permissions: Vec<Permission>,
+    /// Defaults to 90 days, at most 730
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKey {
+    /// Send as `Authorization: ApiKey <key>`. Shown only this once.
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+// Daemon fleet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DaemonHealth {
+    /// Not checked since it was added or changed
+    Unknown,
+    Healthy,
+    /// Could not be reached or timed out
+    Unreachable,
+    /// Answered, but with an error
+    Error,
+}
+
+impl DaemonHealth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DaemonHealth::Unknown => "unknown",
+            DaemonHealth::Healthy => "healthy",
+            DaemonHealth::Unreachable => "unreachable",
+            DaemonHealth::Error => "error",
+        }
+    }
+}
+
+impl FromStr for DaemonHealth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unknown" => Ok(DaemonHealth::Unknown),
+            "healthy" => Ok(DaemonHealth::Healthy),
+            "unreachable" => Ok(DaemonHealth::Unreachable),
+            "error" => Ok(DaemonHealth::Error),
+            _ => Err(format!("Unknown daemon health: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct DaemonDb {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub token: Option<String>,
+    pub enabled: bool,
+    pub health: String,
+    pub health_error: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub last_healthy_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<DaemonDb> for Daemon {
+    fn from(db: DaemonDb) -> Self {
+        Self {
+            id: Uuid::parse_str(&db.id).unwrap_or_default(),
+            name: db.name,
+            url: db.url,
+            has_token: db.token.is_some(),
+            enabled: db.enabled,
+            health: db.health.parse().unwrap_or(DaemonHealth::Unknown),
+            health_error: db.health_error,
+            last_checked_at: db.last_checked_at,
+            last_healthy_at: db.last_healthy_at,
+            created_at: db.created_at,
+            updated_at: db.updated_at,
+        }
+    }
+}
+
+/// A registered rcpdaemon and the result of its last health check.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Daemon {
+    pub id: Uuid,
+    pub name: String,
+    pub url: String,
+    /// Whether requests to the daemon carry a token; the token itself is
+    /// never returned
+    pub has_token: bool,
+    /// Disabled daemons are neither polled nor reachable through the API
+    pub enabled: bool,
+    pub health: DaemonHealth,
+    /// Why the last check failed
+    pub health_error: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub last_healthy_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateDaemon {
+    pub name: String,
+    /// Base URL of the daemon's HTTP API, e.g. `http://10.0.0.5:3030`
+    pub url: String,
+    /// Bearer token the daemon expects
+    pub token: Option<String>,
+    /// Defaults to true
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateDaemon {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    /// Replaces the token; an empty string removes it
+    pub token: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+/// Live status of one daemon in [`FleetStatus`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetDaemonStatus {
+    pub daemon: Daemon,
+    /// `None` if the daemon did not answer; see `error`
+    pub status: Option<ServerStatus>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetStatus {
+    /// Every enabled daemon
+    pub daemons: Vec<FleetDaemonStatus>,
+    pub total: usize,
+    /// Daemons that answered
+    pub reachable: usize,
+    /// Sum over the daemons that answered
+    pub active_sessions: u64,
+}
+
+/// Health of the backend and, as of their last checks, of the daemons.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SystemHealth {
+    /// `healthy` when every enabled daemon is, `degraded` otherwise
+    pub status: String,
+    pub version: String,
+    pub timestamp: DateTime<Utc>,
+    pub daemons: FleetHealth,
+}
+
+/// Enabled daemons counted by the result of their last health check.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct FleetHealth {
+    pub total: usize,
+    pub healthy: usize,
+    pub unreachable: usize,
+    pub error: usize,
+    pub unknown: usize,
+}
+
+/// A session together with the daemon it runs on.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetSession {
+    pub daemon_id: Uuid,
+    pub daemon_name: String,
+    #[serde(flatten)]
+    pub session: Session,
+}
+
+/// A daemon left out of a fleet-wide view because it did not answer.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetError {
+    pub daemon_id: Uuid,
+    pub daemon_name: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetSessions {
+    pub sessions: Vec<FleetSession>,
+    /// Daemons whose sessions are missing from the list
+    pub errors: Vec<FleetError>,
+}
@@ -0,0 +1,266 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{query, query_as, query_scalar};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{AppError, Result},
+    models::{ApiKey, ApiKeyDb, CreateApiKey, Permission},
+    services::auth::hash_token,
+};
+
+/// Makes keys recognisable in configs and secret scanners
+const KEY_PREFIX: &str = "rcpk_";
+/// Characters of the key kept in clear for listings
+const DISPLAY_PREFIX_LEN: usize = 12;
+const DEFAULT_EXPIRY_DAYS: u32 = 90;
+const MAX_EXPIRY_DAYS: u32 = 730;
+const MAX_NAME_LEN: usize = 64;
+/// `last_used_at` is only written when older than this, to spare the database
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+const COLUMNS: &str = "id, name, key_prefix, permissions, user_id, created_at, expires_at, \
+     last_used_at, revoked_at";
+
+pub struct ApiKeyService {
+    db: Database,
+}
+
+impl ApiKeyService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Creates a key acting for user `owner_id`. It may only carry
+    /// permissions in `granted`, the owner's own. Returns the key and its
+    /// stored record.
+    pub async fn create(
+        &self,
+        owner_id: Uuid,
+        granted: &HashSet<Permission>,
+        request: &CreateApiKey,
+    ) -> Result<(String, ApiKey)> {
+        let name = request.name.trim();
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(AppError::Validation(format!(
+                "API key name must be 1 to {} characters",
+                MAX_NAME_LEN
+            )));
+        }
+        if request.permissions.is_empty() {
+            return Err(AppError::Validation(
+                "API key needs at least one permission".to_string(),
+            ));
+        }
+        if let Some(missing) = request.permissions.iter().find(|p| !granted.contains(p)) {
+            return Err(AppError::Unauthorized(format!(
+                "Cannot grant permission '{}' that you do not have",
+                missing
+            )));
+        }
+        let expires_in_days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+        if !(1..=MAX_EXPIRY_DAYS).contains(&expires_in_days) {
+            return Err(AppError::Validation(format!(
+                "expires_in_days must be between 1 and {}",
+                MAX_EXPIRY_DAYS
+            )));
+        }
+
+        let taken: bool = query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM api_keys WHERE name = ? AND revoked_at IS NULL)",
+        )
+        .bind(name)
+        .fetch_one(self.db.pool())
+        .await?;
+        if taken {
+            return Err(AppError::Conflict(format!(
+                "An active API key named '{}' already exists",
+                name
+            )));
+        }
+
+        let key = format!(
+            "{}{}{}",
+            KEY_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let mut permissions: Vec<&str> = request
+            .permissions
+            .iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|p| p.as_str())
+            .collect();
+        permissions.sort();
+        let now = Utc::now();
+
+        let stored = query_as::<_, ApiKeyDb>(&format!(
+            r#"
+            INSERT INTO api_keys (id, name, key_prefix, key_hash, permissions, user_id, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(name)
+        .bind(&key[..DISPLAY_PREFIX_LEN])
+        .bind(hash_token(&key))
+        .bind(serde_json::to_string(&permissions)?)
+        .bind(owner_id.to_string())
+        .bind(now)
+        .bind(now + Duration::days(expires_in_days.into()))
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok((key, stored.into()))
+    }
+
+    /// Every key, newest first, including revoked and expired ones.
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        let keys = query_as::<_, ApiKeyDb>(&format!(
+            "SELECT {COLUMNS} FROM api_keys ORDER BY created_at DESC"
+        ))
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(keys.into_iter().map(ApiKey::from).collect())
+    }
+
+    /// Revokes a key for good. Revoking it again is a no-op.
+    pub async fn revoke(&self, id: Uuid) -> Result<ApiKey> {
+        query("UPDATE api_keys SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(id.to_string())
+            .execute(self.db.pool())
+            .await?;
+
+        let key = query_as::<_, ApiKeyDb>(&format!("SELECT {COLUMNS} FROM api_keys WHERE id = ?"))
+            .bind(id.to_string())
+            .fetch_optional(self.db.pool())
+            .await?
+            .ok_or_else(|| AppError::NotFound("API key not found".to_string()))?;
+        Ok(key.into())
+    }
+
+    /// Looks up a presented key and records that it was used.
+    pub async fn authenticate(&self, key: &str, now: DateTime<Utc>) -> Result<ApiKeyDb> {
+        let stored = query_as::<_, ApiKeyDb>(&format!(
+            "SELECT {COLUMNS} FROM api_keys WHERE key_hash = ?"
+        ))
+        .bind(hash_token(key))
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(|| AppError::Auth("Invalid API key".to_string()))?;
+
+        if stored.revoked_at.is_some() {
+            return Err(AppError::TokenRevoked(
+                "API key has been revoked".to_string(),
+            ));
+        }
+        if stored.expires_at <= now {
+            return Err(AppError::TokenExpired("API key has expired".to_string()));
+        }
+
+        query(
+            "UPDATE api_keys SET last_used_at = ? WHERE id = ? AND (last_used_at IS NULL OR last_used_at < ?)",
+        )
+        .bind(now)
+        .bind(&stored.id)
+        .bind(now - Duration::seconds(LAST_USED_RESOLUTION_SECS))
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn service() -> ApiKeyService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        ApiKeyService::new(db)
+    }
+
+    fn request(name: &str, permissions: &[Permission]) -> CreateApiKey {
+        CreateApiKey {
+            name: name.to_string(),
+            permissions: permissions.to_vec(),
+            expires_in_days: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_carry_only_permissions_their_creator_has() {
+        let keys = service().await;
+        let owner = ADMIN_ID.parse().unwrap();
+        let granted = HashSet::from([Permission::AppsRead, Permission::AuditRead]);
+
+        let refused = keys
+            .create(
+                owner,
+                &granted,
+                &request("ci", &[Permission::AppsRead, Permission::UsersManage]),
+            )
+            .await;
+        assert!(matches!(refused, Err(AppError::Unauthorized(_))));
+
+        let (key, stored) = keys
+            .create(
+                owner,
+                &granted,
+                &request("ci", &[Permission::AuditRead, Permission::AuditRead]),
+            )
+            .await
+            .unwrap();
+        assert!(key.starts_with(&stored.prefix));
+        assert_eq!(stored.permissions, vec![Permission::AuditRead]);
+
+        // Names are unique among active keys
+        let taken = keys
+            .create(owner, &granted, &request("ci", &[Permission::AppsRead]))
+            .await;
+        assert!(matches!(taken, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn authenticates_only_active_keys() {
+        let keys = service().await;
+        let granted = HashSet::from([Permission::AppsRead]);
+        let (key, stored) = keys
+            .create(
+                ADMIN_ID.parse().unwrap(),
+                &granted,
+                &request("ci", &[Permission::AppsRead]),
+            )
+            .await
+            .unwrap();
+        let now = Utc::now();
+
+        assert_eq!(
+            keys.authenticate(&key, now).await.unwrap().id,
+            stored.id.to_string()
+        );
+        assert!(keys.list().await.unwrap()[0].last_used_at.is_some());
+
+        assert!(matches!(
+            keys.authenticate("rcpk_unknown", now).await,
+            Err(AppError::Auth(_))
+        ));
+        assert!(matches!(
+            keys.authenticate(&key, now + Duration::days(91)).await,
+            Err(AppError::TokenExpired(_))
+        ));
+
+        keys.revoke(stored.id).await.unwrap();
+        assert!(matches!(
+            keys.authenticate(&key, now).await,
+            Err(AppError::TokenRevoked(_))
+        ));
+    }
+}
@@ -1,23 +1,22 @@
 use bcrypt::verify;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use serde::{Deserialize, Serialize};
-use sqlx::query_as;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_as};
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
+    auth::{generate_token, Claims},
+    config::AuthConfig,
     db::Database,
     error::{AppError, Result},
-    models::{LoginRequest, User, UserDb},
+    models::{LoginRequest, RefreshTokenDb, User, UserDb},
 };
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    pub sub: String,
-    pub username: String,
-    pub role: String,
-    pub exp: usize,
-}
+/// bcrypt hash, at the cost `hash_password` uses, of a random
+/// password nobody knows. Checked against when the username does not exist,
+/// so that unknown users take as long to refuse as wrong passwords.
+const DUMMY_PASSWORD_HASH: &str = "$2b$12$9DuRaOtZQ3HuQvK2gnKL3.DO4PQJ1jlS5roGCw/nNKx9LRLNekVGm";
 
 pub struct AuthService {
     db: Database,
@@ -39,14 +38,17 @@ impl AuthService {
         }
 
         let user_db = query_as::<_, UserDb>(
-            "SELECT id, username, email, password_hash, role, is_active, created_at, updated_at FROM users WHERE username = ? AND is_active = 1",
+            "SELECT id, username, email, password_hash, role, is_active, created_at, updated_at FROM users WHERE username = ?",
         )
         .bind(&creds.username)
         .fetch_optional(self.db.pool())
         .await?;
 
-        let user_db =
-            user_db.ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+        let Some(user_db) = user_db else {
+            // The outcome does not matter, only the time it takes
+            let _ = verify(&creds.password, DUMMY_PASSWORD_HASH);
+            return Err(AppError::InvalidCredentials);
+        };
 
         // Verify password
         let password_valid = verify(&creds.password, &user_db.password_hash).map_err(|e| {
@@ -54,7 +56,12 @@ impl AuthService {
         })?;
 
         if !password_valid {
-            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+            return Err(AppError::InvalidCredentials);
+        }
+
+        // Only reveal that the account is disabled once the password checked out
+        if !user_db.is_active {
+            return Err(AppError::AccountDisabled("Account is disabled".to_string()));
         }
 
         Ok(user_db.into())
@@ -72,40 +79,354 @@ impl AuthService {
         Ok(user_db.into())
     }
 
-    pub fn create_token(&self, user: &User) -> Result<String> {
-        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret".to_string());
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(24))
-            .expect("valid timestamp")
-            .timestamp() as usize;
+    /// Issues a short-lived access token together with a new refresh token.
+    pub async fn issue_tokens(&self, user: &User, auth: &AuthConfig) -> Result<TokenPair> {
+        let access_ttl = Duration::minutes(auth.access_token_ttl_minutes);
+        let access_token = generate_token(user, &auth.jwt_secret, access_ttl)?;
 
-        let claims = Claims {
-            sub: user.id.to_string(),
-            username: user.username.clone(),
-            role: user.role.to_string(),
-            exp: expiration,
-        };
+        let refresh_token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let now = Utc::now();
+
+        query(
+            "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(user.id.to_string())
+        .bind(hash_token(&refresh_token))
+        .bind(now + Duration::days(auth.refresh_token_ttl_days))
+        .bind(now)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: access_ttl.num_seconds(),
+        })
+    }
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_bytes()),
+    /// Exchanges a refresh token for a new token pair. The presented token is
+    /// revoked, so each refresh token can be used exactly once.
+    pub async fn refresh(
+        &self,
+        refresh_token: &str,
+        auth: &AuthConfig,
+    ) -> Result<(User, TokenPair)> {
+        let stored = query_as::<_, RefreshTokenDb>(
+            "SELECT id, user_id, expires_at, revoked_at FROM refresh_tokens WHERE token_hash = ?",
         )
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Token creation failed: {}", e)))?;
+        .bind(hash_token(refresh_token))
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(|| AppError::Auth("Invalid refresh token".to_string()))?;
+
+        let user_id = Uuid::parse_str(&stored.user_id)
+            .map_err(|_| AppError::Auth("Invalid refresh token".to_string()))?;
+
+        if stored.revoked_at.is_some() {
+            // A rotated token coming back means it was copied somewhere; cut
+            // off every session of the user rather than guess which is legit.
+            warn!(
+                "Revoked refresh token reused for user {}, revoking all of their tokens",
+                user_id
+            );
+            self.revoke_user_tokens(user_id).await?;
+            return Err(AppError::TokenRevoked(
+                "Refresh token has been revoked".to_string(),
+            ));
+        }
+
+        if stored.expires_at <= Utc::now() {
+            return Err(AppError::TokenExpired(
+                "Refresh token has expired".to_string(),
+            ));
+        }
+
+        let user = self
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|err| match err {
+                AppError::NotFound(_) => {
+                    AppError::AccountDisabled("Account is disabled or no longer exists".to_string())
+                }
+                other => other,
+            })?;
+
+        let rotated =
+            query("UPDATE refresh_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+                .bind(Utc::now())
+                .bind(&stored.id)
+                .execute(self.db.pool())
+                .await?;
 
-        Ok(token)
+        if rotated.rows_affected() == 0 {
+            // Lost a race against another refresh with the same token
+            return Err(AppError::TokenRevoked(
+                "Refresh token has been revoked".to_string(),
+            ));
+        }
+
+        let tokens = self.issue_tokens(&user, auth).await?;
+        Ok((user, tokens))
     }
 
-    pub fn verify_token(&self, token: &str) -> Result<Claims> {
-        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret".to_string());
+    /// Adds an access token to the deny-list until it would have expired anyway.
+    pub async fn revoke_access_token(&self, claims: &Claims) -> Result<()> {
+        let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(Utc::now);
+
+        query("INSERT OR IGNORE INTO revoked_tokens (jti, user_id, expires_at, revoked_at) VALUES (?, ?, ?, ?)")
+            .bind(&claims.jti)
+            .bind(&claims.sub)
+            .bind(expires_at)
+            .bind(Utc::now())
+            .execute(self.db.pool())
+            .await?;
+
+        self.purge_expired().await
+    }
 
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &Validation::default(),
+    /// Revokes a single refresh token, if it belongs to `user_id`.
+    pub async fn revoke_refresh_token(&self, user_id: Uuid, refresh_token: &str) -> Result<()> {
+        query(
+            "UPDATE refresh_tokens SET revoked_at = ? WHERE token_hash = ? AND user_id = ? AND revoked_at IS NULL",
         )
-        .map_err(|e| AppError::Unauthorized(format!("Token verification failed: {}", e)))?;
+        .bind(Utc::now())
+        .bind(hash_token(refresh_token))
+        .bind(user_id.to_string())
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Revokes every outstanding refresh token of a user.
+    pub async fn revoke_user_tokens(&self, user_id: Uuid) -> Result<()> {
+        query("UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(user_id.to_string())
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// True if the access token was revoked by a logout, or issued before its
+    /// user last changed their password.
+    pub async fn is_token_revoked(&self, claims: &Claims) -> Result<bool> {
+        let revoked: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = ?)")
+                .bind(&claims.jti)
+                .fetch_one(self.db.pool())
+                .await?;
+        if revoked {
+            return Ok(true);
+        }
+
+        let password_changed_at: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT password_changed_at FROM users WHERE id = ?")
+                .bind(&claims.sub)
+                .fetch_optional(self.db.pool())
+                .await?
+                .flatten();
+        // `iat` has whole seconds; tokens from the second of the change,
+        // such as the ones handed out with it, stay valid
+        Ok(password_changed_at.is_some_and(|changed| claims.iat < changed.timestamp()))
+    }
+
+    /// Drops deny-list entries and refresh tokens that can no longer be used.
+    async fn purge_expired(&self) -> Result<()> {
+        let now = Utc::now();
+        query("DELETE FROM revoked_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.db.pool())
+            .await?;
+        query("DELETE FROM refresh_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of the access token in seconds
+    pub expires_in: i64,
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth::validate_token, services::password::PasswordService};
+
+    const ADMIN_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn service() -> AuthService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        AuthService::new(db)
+    }
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            jwt_secret: "test-secret".to_string(),
+            ..AuthConfig::default()
+        }
+    }
+
+    fn login(username: &str, password: &str) -> LoginRequest {
+        LoginRequest {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn dummy_hash_costs_as_much_as_real_ones() {
+        let cost = format!("$2b${:02}$", bcrypt::DEFAULT_COST);
+        assert!(DUMMY_PASSWORD_HASH.starts_with(&cost));
+        assert!(!verify("admin123", DUMMY_PASSWORD_HASH).unwrap());
+    }
+
+    #[tokio::test]
+    async fn unknown_users_and_wrong_passwords_look_the_same() {
+        let auth = service().await;
+
+        assert!(matches!(
+            auth.authenticate(&login("nobody", "admin123")).await,
+            Err(AppError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            auth.authenticate(&login("admin", "wrong password")).await,
+            Err(AppError::InvalidCredentials)
+        ));
+        assert!(auth.authenticate(&login("admin", "admin123")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens_rotate() {
+        let auth = service().await;
+        let admin = auth
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let first = auth.issue_tokens(&admin, &config()).await.unwrap();
+
+        let (user, second) = auth.refresh(&first.refresh_token, &config()).await.unwrap();
+        assert_eq!(user.id, admin.id);
+        assert_ne!(second.refresh_token, first.refresh_token);
+
+        let (_, third) = auth
+            .refresh(&second.refresh_token, &config())
+            .await
+            .unwrap();
+        assert_ne!(third.refresh_token, second.refresh_token);
+
+        assert!(matches!(
+            auth.refresh("not a token", &config()).await,
+            Err(AppError::Auth(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reused_refresh_token_revokes_every_session() {
+        let auth = service().await;
+        let admin = auth
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let stolen = auth.issue_tokens(&admin, &config()).await.unwrap();
+        let other_device = auth.issue_tokens(&admin, &config()).await.unwrap();
+        let (_, rotated) = auth
+            .refresh(&stolen.refresh_token, &config())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            auth.refresh(&stolen.refresh_token, &config()).await,
+            Err(AppError::TokenRevoked(_))
+        ));
+        // Both the rotated token and the user's other sessions are gone
+        for token in [&rotated.refresh_token, &other_device.refresh_token] {
+            assert!(matches!(
+                auth.refresh(token, &config()).await,
+                Err(AppError::TokenRevoked(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_refresh_tokens_are_refused() {
+        let auth = service().await;
+        let admin = auth
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let config = AuthConfig {
+            refresh_token_ttl_days: 0,
+            ..config()
+        };
+        let tokens = auth.issue_tokens(&admin, &config).await.unwrap();
+
+        assert!(matches!(
+            auth.refresh(&tokens.refresh_token, &config).await,
+            Err(AppError::TokenExpired(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_only_its_access_token() {
+        let auth = service().await;
+        let admin = auth
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let claims = |token: &str| validate_token(token, "test-secret").unwrap();
+        let logged_out = claims(
+            &auth
+                .issue_tokens(&admin, &config())
+                .await
+                .unwrap()
+                .access_token,
+        );
+        let other = claims(
+            &auth
+                .issue_tokens(&admin, &config())
+                .await
+                .unwrap()
+                .access_token,
+        );
+
+        auth.revoke_access_token(&logged_out).await.unwrap();
+        assert!(auth.is_token_revoked(&logged_out).await.unwrap());
+        assert!(!auth.is_token_revoked(&other).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn password_change_revokes_earlier_tokens() {
+        let auth = service().await;
+        let admin = auth
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        let tokens = auth.issue_tokens(&admin, &config()).await.unwrap();
+        let claims = validate_token(&tokens.access_token, "test-secret").unwrap();
+        assert!(!auth.is_token_revoked(&claims).await.unwrap());
+
+        // A second later, as tokens from the second of the change stay valid
+        let changed_at = Utc::now() + Duration::seconds(1);
+        PasswordService::new(auth.db.clone(), &config().password)
+            .change(&admin, "a much longer secret", changed_at)
+            .await
+            .unwrap();
 
-        Ok(token_data.claims)
+        assert!(auth.is_token_revoked(&claims).await.unwrap());
+        assert!(matches!(
+            auth.refresh(&tokens.refresh_token, &config()).await,
+            Err(AppError::TokenRevoked(_))
+        ));
     }
 }
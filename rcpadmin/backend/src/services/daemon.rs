@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    config::DaemonConfig,
+    db::Database,
+    error::{AppError, Result},
+    models::{CreateDaemon, DaemonDb, DaemonHealth, UpdateDaemon},
+};
+
+/// The daemon configured under `[rcpdaemon]` before the backend managed a
+/// fleet; data recorded before then belongs to it.
+pub const DEFAULT_DAEMON_ID: Uuid = Uuid::from_u128(1);
+
+const MAX_NAME_LEN: usize = 64;
+
+const COLUMNS: &str = "id, name, url, token, enabled, health, health_error, last_checked_at, \
+     last_healthy_at, created_at, updated_at";
+
+pub struct DaemonService {
+    db: Database,
+}
+
+impl DaemonService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Fills in the `default` daemon from `[rcpdaemon]` if it has no URL
+    /// yet, i.e. on the first start after upgrading.
+    pub async fn init_default(&self, config: &DaemonConfig) -> Result<()> {
+        query("UPDATE daemons SET url = ?, token = ? WHERE id = ? AND url = ''")
+            .bind(config.url.trim_end_matches('/'))
+            .bind(&config.token)
+            .bind(DEFAULT_DAEMON_ID.to_string())
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Every daemon by name, with its token for building clients.
+    pub async fn list(&self) -> Result<Vec<DaemonDb>> {
+        let daemons =
+            query_as::<_, DaemonDb>(&format!("SELECT {COLUMNS} FROM daemons ORDER BY name ASC"))
+                .fetch_all(self.db.pool())
+                .await?;
+        Ok(daemons)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<DaemonDb> {
+        query_as::<_, DaemonDb>(&format!("SELECT {COLUMNS} FROM daemons WHERE id = ?"))
+            .bind(id.to_string())
+            .fetch_optional(self.db.pool())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Daemon not found".to_string()))
+    }
+
+    pub async fn create(&self, request: &CreateDaemon) -> Result<DaemonDb> {
+        let name = validate_name(&request.name)?;
+        let url = validate_url(&request.url)?;
+        let now = Utc::now();
+
+        let daemon = query_as::<_, DaemonDb>(&format!(
+            r#"
+            INSERT INTO daemons (id, name, url, token, enabled, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(name)
+        .bind(url)
+        .bind(non_empty(request.token.as_deref()))
+        .bind(request.enabled.unwrap_or(true))
+        .bind(now)
+        .bind(now)
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|e| name_conflict(e, name))?;
+
+        Ok(daemon)
+    }
+
+    /// Changing where or how the daemon is reached resets its health to
+    /// unknown until the next check.
+    pub async fn update(&self, id: Uuid, update: &UpdateDaemon) -> Result<DaemonDb> {
+        let name = update.name.as_deref().map(validate_name).transpose()?;
+        let url = update.url.as_deref().map(validate_url).transpose()?;
+        let reconnect = url.is_some() || update.token.is_some();
+
+        let daemon = query_as::<_, DaemonDb>(&format!(
+            r#"
+            UPDATE daemons
+            SET name = COALESCE(?1, name),
+                url = COALESCE(?2, url),
+                token = CASE WHEN ?3 THEN ?4 ELSE token END,
+                enabled = COALESCE(?5, enabled),
+                health = CASE WHEN ?6 THEN 'unknown' ELSE health END,
+                health_error = CASE WHEN ?6 THEN NULL ELSE health_error END,
+                updated_at = ?7
+            WHERE id = ?8
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(name)
+        .bind(url)
+        .bind(update.token.is_some())
+        .bind(non_empty(update.token.as_deref()))
+        .bind(update.enabled)
+        .bind(reconnect)
+        .bind(Utc::now())
+        .bind(id.to_string())
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|e| name_conflict(e, name.unwrap_or_default()))?
+        .ok_or_else(|| AppError::NotFound("Daemon not found".to_string()))?;
+
+        Ok(daemon)
+    }
+
+    /// Removes the daemon and its recorded metrics. Launch history is kept.
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let mut tx = self.db.pool().begin().await?;
+        let deleted = query("DELETE FROM daemons WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(AppError::NotFound("Daemon not found".to_string()));
+        }
+        for table in ["metrics_samples", "session_metrics_samples"] {
+            query(&format!("DELETE FROM {table} WHERE daemon_id = ?"))
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Stores the outcome of a health check. Returns the previous health, or
+    /// `None` if the daemon was deleted meanwhile.
+    pub async fn record_health(
+        &self,
+        id: Uuid,
+        health: DaemonHealth,
+        error: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DaemonHealth>> {
+        let previous: Option<String> = query_scalar("SELECT health FROM daemons WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(self.db.pool())
+            .await?;
+
+        query(
+            r#"
+            UPDATE daemons
+            SET health = ?1,
+                health_error = ?2,
+                last_checked_at = ?3,
+                last_healthy_at = CASE WHEN ?1 = 'healthy' THEN ?3 ELSE last_healthy_at END
+            WHERE id = ?4
+            "#,
+        )
+        .bind(health.as_str())
+        .bind(error)
+        .bind(now)
+        .bind(id.to_string())
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(previous.map(|h| h.parse().unwrap_or(DaemonHealth::Unknown)))
+    }
+}
+
+fn validate_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(AppError::Validation(format!(
+            "Daemon name must be 1 to {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    Ok(name)
+}
+
+/// Trims `url` and checks that it is an http:// or https:// URL with a host.
+pub fn validate_url(url: &str) -> Result<&str> {
+    let url = url.trim().trim_end_matches('/');
+    let host = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"));
+    match host {
+        Some(host) if !host.is_empty() => Ok(url),
+        _ => Err(AppError::Validation(
+            "Daemon URL must start with http:// or https://".to_string(),
+        )),
+    }
+}
+
+fn non_empty(token: Option<&str>) -> Option<&str> {
+    token.filter(|t| !t.is_empty())
+}
+
+/// Maps a UNIQUE constraint violation on `daemons.name` to a conflict.
+fn name_conflict(err: sqlx::Error, name: &str) -> AppError {
+    match err {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            AppError::Conflict(format!("A daemon named '{}' already exists", name))
+        }
+        other => other.into(),
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use sqlx::{query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::Result,
+    models::{LaunchHistory, LaunchHistoryQuery, LaunchRecord, LaunchRecordDb, LaunchStatus},
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+const COLUMNS: &str = "id, application_id, user_id, username, arguments, session_id, status, \
+     exit_code, error, launched_at, ended_at, duration_ms";
+
+pub struct NewLaunch {
+    pub daemon_id: Uuid,
+    pub application_id: String,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub arguments: Vec<String>,
+    /// `Ok` with the daemon's session id (if it reported one), or the reason
+    /// the launch failed
+    pub outcome: std::result::Result<Option<Uuid>, String>,
+}
+
+pub struct LaunchHistoryService {
+    db: Database,
+}
+
+impl LaunchHistoryService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn record_launch(&self, launch: NewLaunch) -> Result<LaunchRecord> {
+        let now = Utc::now();
+        let (status, session_id, error, ended_at) = match launch.outcome {
+            Ok(session_id) => (LaunchStatus::Running, session_id, None, None),
+            Err(error) => (LaunchStatus::Failed, None, Some(error), Some(now)),
+        };
+
+        let record = query_as::<_, LaunchRecordDb>(&format!(
+            r#"
+            INSERT INTO launch_history (daemon_id, application_id, user_id, username, arguments, session_id, status, error, launched_at, ended_at, duration_ms)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(launch.daemon_id.to_string())
+        .bind(&launch.application_id)
+        .bind(&launch.user_id)
+        .bind(&launch.username)
+        .bind(serde_json::to_string(&launch.arguments)?)
+        .bind(session_id.map(|id| id.to_string()))
+        .bind(status.as_str())
+        .bind(error)
+        .bind(now)
+        .bind(ended_at)
+        .bind(ended_at.map(|_| 0i64))
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(record.into())
+    }
+
+    /// Marks the application's most recent running launch on the daemon as
+    /// stopped. Returns `None` if nothing was running according to the
+    /// history.
+    pub async fn record_stop(
+        &self,
+        daemon_id: Uuid,
+        application_id: &str,
+        exit_code: Option<i64>,
+    ) -> Result<Option<LaunchRecord>> {
+        let running = query_as::<_, LaunchRecordDb>(&format!(
+            r#"
+            SELECT {COLUMNS} FROM launch_history
+            WHERE daemon_id = ? AND application_id = ? AND status = 'running'
+            ORDER BY id DESC
+            LIMIT 1
+            "#
+        ))
+        .bind(daemon_id.to_string())
+        .bind(application_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match running {
+            Some(launch) => {
+                let record = self
+                    .finish(&launch, LaunchStatus::Stopped, exit_code, Utc::now())
+                    .await?;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Marks launches whose daemon session has gone away as exited.
+    pub async fn record_session_end(
+        &self,
+        session_id: Uuid,
+        ended_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let running = query_as::<_, LaunchRecordDb>(&format!(
+            "SELECT {COLUMNS} FROM launch_history WHERE session_id = ? AND status = 'running'"
+        ))
+        .bind(session_id.to_string())
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for launch in &running {
+            self.finish(launch, LaunchStatus::Exited, None, ended_at)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        launch: &LaunchRecordDb,
+        status: LaunchStatus,
+        exit_code: Option<i64>,
+        ended_at: DateTime<Utc>,
+    ) -> Result<LaunchRecord> {
+        let duration_ms = (ended_at - launch.launched_at).num_milliseconds().max(0);
+
+        let record = query_as::<_, LaunchRecordDb>(&format!(
+            r#"
+            UPDATE launch_history
+            SET status = ?, exit_code = ?, ended_at = ?, duration_ms = ?
+            WHERE id = ?
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(status.as_str())
+        .bind(exit_code)
+        .bind(ended_at)
+        .bind(duration_ms)
+        .bind(launch.id)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(record.into())
+    }
+
+    /// Launches of an application on one daemon, newest first.
+    pub async fn list(
+        &self,
+        daemon_id: Uuid,
+        application_id: &str,
+        filter: &LaunchHistoryQuery,
+    ) -> Result<LaunchHistory> {
+        let page = filter.page.unwrap_or(1).max(1);
+        let per_page = filter
+            .per_page
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let status = filter.status.map(|s| s.as_str());
+
+        let total: i64 = query_scalar(
+            "SELECT COUNT(*) FROM launch_history WHERE daemon_id = ?1 AND application_id = ?2 AND (?3 IS NULL OR status = ?3)",
+        )
+        .bind(daemon_id.to_string())
+        .bind(application_id)
+        .bind(status)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        let launches = query_as::<_, LaunchRecordDb>(&format!(
+            r#"
+            SELECT {COLUMNS} FROM launch_history
+            WHERE daemon_id = ?1 AND application_id = ?2 AND (?3 IS NULL OR status = ?3)
+            ORDER BY id DESC
+            LIMIT ?4 OFFSET ?5
+            "#
+        ))
+        .bind(daemon_id.to_string())
+        .bind(application_id)
+        .bind(status)
+        .bind(per_page as i64)
+        .bind((page as i64 - 1) * per_page as i64)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(LaunchHistory {
+            launches: launches.into_iter().map(Into::into).collect(),
+            total,
+            page,
+            per_page,
+        })
+    }
+}
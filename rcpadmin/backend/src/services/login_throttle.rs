@@ -0,0 +1,365 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{query, query_scalar};
+
+use crate::{config::LockoutConfig, db::Database, error::Result};
+
+/// What a failed-login counter is kept for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleScope {
+    Ip,
+    Username,
+}
+
+impl ThrottleScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleScope::Ip => "ip",
+            ThrottleScope::Username => "username",
+        }
+    }
+}
+
+/// A counter that just reached `lockout_after` failures.
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub scope: ThrottleScope,
+    pub subject: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Failed-login counters with exponential backoff, see [`LockoutConfig`].
+/// Time-dependent methods take `now` rather than reading the clock, so the
+/// caller decides what time it is.
+pub struct LoginThrottleService {
+    db: Database,
+    policy: LockoutConfig,
+}
+
+impl LoginThrottleService {
+    pub fn new(db: Database, policy: &LockoutConfig) -> Self {
+        Self {
+            db,
+            policy: policy.clone(),
+        }
+    }
+
+    /// Whole seconds until both `ip` and `username` may try again, or `None`
+    /// if neither is blocked.
+    pub async fn retry_after(
+        &self,
+        ip: Option<&str>,
+        username: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<i64>> {
+        let blocked_until: Option<DateTime<Utc>> = query_scalar(
+            r#"
+            SELECT blocked_until FROM login_failures
+            WHERE ((scope = 'ip' AND subject = ?) OR (scope = 'username' AND subject = ?))
+              AND blocked_until > ?
+            ORDER BY blocked_until DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(ip)
+        .bind(username)
+        .bind(now)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        // Round up, so a client honouring Retry-After is not refused again
+        Ok(blocked_until.map(|until| ((until - now).num_milliseconds() + 999) / 1000))
+    }
+
+    /// Counts a failed attempt against `ip` and `username` and blocks them as
+    /// the policy says. Returns the lockouts this failure started.
+    pub async fn record_failure(
+        &self,
+        ip: Option<&str>,
+        username: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Lockout>> {
+        let subjects = ip
+            .map(|ip| (ThrottleScope::Ip, ip))
+            .into_iter()
+            .chain([(ThrottleScope::Username, username)]);
+
+        let mut lockouts = Vec::new();
+        for (scope, subject) in subjects {
+            let failures: i64 = query_scalar(
+                r#"
+                INSERT INTO login_failures (scope, subject, failures, last_failure_at)
+                VALUES (?1, ?2, 1, ?3)
+                ON CONFLICT (scope, subject) DO UPDATE SET
+                    failures = CASE WHEN last_failure_at < ?4 THEN 1 ELSE failures + 1 END,
+                    last_failure_at = ?3
+                RETURNING failures
+                "#,
+            )
+            .bind(scope.as_str())
+            .bind(subject)
+            .bind(now)
+            .bind(now - Duration::minutes(self.policy.reset_after_minutes))
+            .fetch_one(self.db.pool())
+            .await?;
+
+            let failures = u32::try_from(failures).unwrap_or(u32::MAX);
+            let Some(delay) = block_duration(&self.policy, failures) else {
+                continue;
+            };
+            let until = now + delay;
+            query("UPDATE login_failures SET blocked_until = ? WHERE scope = ? AND subject = ?")
+                .bind(until)
+                .bind(scope.as_str())
+                .bind(subject)
+                .execute(self.db.pool())
+                .await?;
+
+            if failures == self.policy.lockout_after {
+                lockouts.push(Lockout {
+                    scope,
+                    subject: subject.to_string(),
+                    until,
+                });
+            }
+        }
+        Ok(lockouts)
+    }
+
+    /// Forgets the failures of `ip` and `username` after a successful login.
+    pub async fn record_success(&self, ip: Option<&str>, username: &str) -> Result<()> {
+        query(
+            "DELETE FROM login_failures WHERE (scope = 'ip' AND subject = ?) OR (scope = 'username' AND subject = ?)",
+        )
+        .bind(ip)
+        .bind(username)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Clears the failures of `username`, lifting any block. Returns whether
+    /// there were any.
+    pub async fn unlock_username(&self, username: &str) -> Result<bool> {
+        let result = query("DELETE FROM login_failures WHERE scope = 'username' AND subject = ?")
+            .bind(username)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// How long `failures` consecutive failures block further attempts.
+pub fn block_duration(policy: &LockoutConfig, failures: u32) -> Option<Duration> {
+    if failures >= policy.lockout_after {
+        return Some(Duration::minutes(policy.lockout_minutes));
+    }
+    let backoffs = failures.checked_sub(policy.free_attempts)?.checked_sub(1)?;
+    let secs = policy
+        .backoff_base_secs
+        .saturating_mul(1i64 << backoffs.min(32))
+        .min(policy.backoff_max_secs);
+    Some(Duration::seconds(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: &str = "192.0.2.1";
+
+    fn policy() -> LockoutConfig {
+        LockoutConfig {
+            free_attempts: 2,
+            backoff_base_secs: 1,
+            backoff_max_secs: 4,
+            lockout_after: 6,
+            lockout_minutes: 15,
+            reset_after_minutes: 30,
+        }
+    }
+
+    async fn throttle() -> LoginThrottleService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        LoginThrottleService::new(db, &policy())
+    }
+
+    fn start() -> DateTime<Utc> {
+        "2026-01-01T12:00:00Z".parse().unwrap()
+    }
+
+    /// Fails `count` times, one second apart from `now` on, and returns the
+    /// time of the last failure.
+    async fn fail(
+        throttle: &LoginThrottleService,
+        ip: Option<&str>,
+        username: &str,
+        count: u32,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let mut at = now;
+        for i in 0..count {
+            at = now + Duration::seconds(i.into());
+            throttle.record_failure(ip, username, at).await.unwrap();
+        }
+        at
+    }
+
+    #[tokio::test]
+    async fn ip_is_blocked_across_usernames() {
+        let throttle = throttle().await;
+        let now = start();
+
+        throttle
+            .record_failure(Some(IP), "alice", now)
+            .await
+            .unwrap();
+        throttle.record_failure(Some(IP), "bob", now).await.unwrap();
+        assert_eq!(
+            throttle.retry_after(Some(IP), "carol", now).await.unwrap(),
+            None
+        );
+
+        throttle
+            .record_failure(Some(IP), "carol", now)
+            .await
+            .unwrap();
+        assert_eq!(
+            throttle.retry_after(Some(IP), "dave", now).await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            throttle
+                .retry_after(Some("192.0.2.2"), "dave", now)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn username_is_blocked_across_ips() {
+        let throttle = throttle().await;
+        let now = start();
+
+        for ip in ["192.0.2.1", "192.0.2.2", "192.0.2.3"] {
+            throttle
+                .record_failure(Some(ip), "alice", now)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            throttle
+                .retry_after(Some("192.0.2.4"), "alice", now)
+                .await
+                .unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            throttle
+                .retry_after(Some("192.0.2.4"), "bob", now)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn backoff_doubles_up_to_the_maximum() {
+        let throttle = throttle().await;
+        let mut now = start();
+
+        fail(&throttle, None, "alice", 2, now).await;
+        let mut delays = Vec::new();
+        for _ in 0..3 {
+            now += Duration::minutes(1);
+            throttle.record_failure(None, "alice", now).await.unwrap();
+            delays.push(throttle.retry_after(None, "alice", now).await.unwrap());
+        }
+        assert_eq!(delays, [Some(1), Some(2), Some(4)]);
+
+        let policy = policy();
+        assert_eq!(block_duration(&policy, 2), None);
+        assert_eq!(block_duration(&policy, 5), Some(Duration::seconds(4)));
+        assert_eq!(block_duration(&policy, 6), Some(Duration::minutes(15)));
+    }
+
+    #[tokio::test]
+    async fn lockout_is_reported_once_and_expires() {
+        let throttle = throttle().await;
+        let now = start();
+
+        let last = fail(&throttle, Some(IP), "alice", 5, now).await;
+        let locked_at = last + Duration::seconds(1);
+        let lockouts = throttle
+            .record_failure(Some(IP), "alice", locked_at)
+            .await
+            .unwrap();
+        let until = locked_at + Duration::minutes(15);
+        assert_eq!(lockouts.len(), 2);
+        assert!(lockouts.iter().all(|lockout| lockout.until == until));
+        assert!(lockouts
+            .iter()
+            .any(|l| l.scope == ThrottleScope::Username && l.subject == "alice"));
+
+        let again = throttle
+            .record_failure(Some(IP), "alice", locked_at)
+            .await
+            .unwrap();
+        assert!(again.is_empty());
+
+        let almost = until - Duration::seconds(1);
+        assert!(throttle
+            .retry_after(Some(IP), "alice", almost)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            throttle
+                .retry_after(Some(IP), "alice", until)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_counters() {
+        let throttle = throttle().await;
+        let now = start();
+
+        let last = fail(&throttle, Some(IP), "alice", 3, now).await;
+        assert!(throttle
+            .retry_after(Some(IP), "alice", last)
+            .await
+            .unwrap()
+            .is_some());
+
+        throttle.record_success(Some(IP), "alice").await.unwrap();
+        assert_eq!(
+            throttle.retry_after(Some(IP), "alice", last).await.unwrap(),
+            None
+        );
+
+        // Counting starts over, so the free attempts are free again
+        let last = fail(&throttle, Some(IP), "alice", 2, last).await;
+        assert_eq!(
+            throttle.retry_after(Some(IP), "alice", last).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn quiet_period_resets_the_counters() {
+        let throttle = throttle().await;
+        let now = start();
+
+        fail(&throttle, None, "alice", 2, now).await;
+        let later = now + Duration::minutes(31);
+        throttle.record_failure(None, "alice", later).await.unwrap();
+        assert_eq!(
+            throttle.retry_after(None, "alice", later).await.unwrap(),
+            None
+        );
+    }
+}
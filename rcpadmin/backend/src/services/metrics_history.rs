@@ -0,0 +1,568 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use sqlx::{query, query_as, FromRow};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{AppError, Result},
+    models::{
+        MetricsRangeQuery, MetricsSeries, Session, SessionMetricsPoint, SessionStatus,
+        SystemMetrics, SystemMetricsPoint,
+    },
+};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+/// `resolution` of the samples written by the recorder
+const RAW: i64 = 0;
+
+/// Range returned when the query gives no `from`
+const DEFAULT_RANGE_SECS: i64 = HOUR;
+/// Points aimed for when the query gives no `step`
+const DEFAULT_POINTS: i64 = 300;
+const MAX_POINTS: i64 = 5_000;
+
+struct Tier {
+    resolution: i64,
+    /// `None` for the coarsest tier, which is kept for the configured
+    /// `metrics_retention_days`
+    retention_secs: Option<i64>,
+}
+
+/// Storage tiers, finest first. Each rollup tier averages the one before it,
+/// so a week-long chart reads a few thousand rows instead of sixty thousand.
+const TIERS: [Tier; 3] = [
+    Tier {
+        resolution: RAW,
+        retention_secs: Some(2 * DAY),
+    },
+    Tier {
+        resolution: 5 * MINUTE,
+        retention_secs: Some(14 * DAY),
+    },
+    Tier {
+        resolution: HOUR,
+        retention_secs: None,
+    },
+];
+
+#[derive(FromRow)]
+struct SystemMetricsRow {
+    bucket: i64,
+    cpu_usage: f64,
+    memory_usage: i64,
+    total_memory: i64,
+    disk_usage: i64,
+    total_disk: i64,
+    network_rx: i64,
+    network_tx: i64,
+    active_sessions: f64,
+}
+
+#[derive(FromRow)]
+struct SessionMetricsRow {
+    bucket: i64,
+    cpu_usage: f64,
+    memory_usage: i64,
+    network_rx: i64,
+    network_tx: i64,
+}
+
+/// A validated range query, in unix seconds.
+struct Window {
+    from: i64,
+    to: i64,
+    step: i64,
+    resolution: i64,
+}
+
+impl Window {
+    fn resolve(range: &MetricsRangeQuery, now: DateTime<Utc>) -> Result<Self> {
+        let to = range.to.unwrap_or(now);
+        let from = range
+            .from
+            .unwrap_or(to - Duration::seconds(DEFAULT_RANGE_SECS));
+
+        if from >= to {
+            return Err(AppError::Validation(
+                "'from' must be earlier than 'to'".to_string(),
+            ));
+        }
+
+        let span = (to - from).num_seconds().max(1);
+        let step = match range.step {
+            Some(step) if step < 1 => {
+                return Err(AppError::Validation(
+                    "'step' must be at least 1 second".to_string(),
+                ))
+            }
+            Some(step) => step,
+            None => ((span + DEFAULT_POINTS - 1) / DEFAULT_POINTS).max(1),
+        };
+
+        // Prefer the coarsest tier that still has data back to `from` and is
+        // no coarser than the step; the last tier is the fallback
+        let age = (now - from).num_seconds();
+        let covering: Vec<&Tier> = TIERS
+            .iter()
+            .filter(|t| t.retention_secs.is_none_or(|r| age <= r))
+            .collect();
+        let tier = covering
+            .iter()
+            .rev()
+            .find(|t| t.resolution <= step)
+            .or_else(|| covering.first())
+            .expect("the coarsest tier always covers the range");
+
+        let step = step.max(tier.resolution);
+        if span / step > MAX_POINTS {
+            return Err(AppError::Validation(format!(
+                "A {}s range at a {}s step exceeds {} points; use a larger step",
+                span, step, MAX_POINTS
+            )));
+        }
+
+        Ok(Self {
+            from: from.timestamp(),
+            to: to.timestamp(),
+            step,
+            resolution: tier.resolution,
+        })
+    }
+
+    fn series<T>(&self, points: Vec<T>) -> MetricsSeries<T> {
+        MetricsSeries {
+            from: timestamp(self.from),
+            to: timestamp(self.to),
+            step: self.step,
+            points,
+        }
+    }
+}
+
+fn timestamp(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(secs, 0).single().unwrap_or_default()
+}
+
+pub struct MetricsHistoryService {
+    db: Database,
+}
+
+impl MetricsHistoryService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Stores one raw sample of a daemon's host and of every session on it.
+    pub async fn record(
+        &self,
+        daemon_id: Uuid,
+        at: DateTime<Utc>,
+        system: &SystemMetrics,
+        sessions: &[Session],
+    ) -> Result<()> {
+        let sampled_at = at.timestamp();
+        let active_sessions = sessions
+            .iter()
+            .filter(|s| s.status == SessionStatus::Active)
+            .count() as i64;
+        let network_rx: u64 = sessions.iter().map(|s| s.metrics.network_rx).sum();
+        let network_tx: u64 = sessions.iter().map(|s| s.metrics.network_tx).sum();
+
+        let mut tx = self.db.pool().begin().await?;
+
+        query(
+            r#"
+            INSERT INTO metrics_samples (daemon_id, resolution, sampled_at, cpu_usage, memory_usage, total_memory, disk_usage, total_disk, network_rx, network_tx, active_sessions)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(daemon_id.to_string())
+        .bind(RAW)
+        .bind(sampled_at)
+        .bind(system.cpu_usage)
+        .bind(system.memory_usage as i64)
+        .bind(system.total_memory as i64)
+        .bind(system.disk_usage as i64)
+        .bind(system.total_disk as i64)
+        .bind(network_rx as i64)
+        .bind(network_tx as i64)
+        .bind(active_sessions)
+        .execute(&mut *tx)
+        .await?;
+
+        for session in sessions {
+            query(
+                r#"
+                INSERT INTO session_metrics_samples (daemon_id, session_id, resolution, sampled_at, cpu_usage, memory_usage, network_rx, network_tx)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(daemon_id.to_string())
+            .bind(session.id.to_string())
+            .bind(RAW)
+            .bind(sampled_at)
+            .bind(session.metrics.cpu_usage)
+            .bind(session.metrics.memory_usage as i64)
+            .bind(session.metrics.network_rx as i64)
+            .bind(session.metrics.network_tx as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Rolls every complete bucket of each tier up into the next one, per
+    /// daemon. Only buckets after the daemon's newest existing rollup are
+    /// written, so this is cheap to run repeatedly.
+    pub async fn downsample(&self, now: DateTime<Utc>) -> Result<()> {
+        for pair in TIERS.windows(2) {
+            let (source, target) = (pair[0].resolution, pair[1].resolution);
+            let end = now.timestamp() / target * target;
+
+            query(
+                r#"
+                INSERT INTO metrics_samples (daemon_id, resolution, sampled_at, cpu_usage, memory_usage, total_memory, disk_usage, total_disk, network_rx, network_tx, active_sessions)
+                SELECT s.daemon_id, ?1, (s.sampled_at / ?1) * ?1 AS bucket, AVG(s.cpu_usage), CAST(AVG(s.memory_usage) AS INTEGER),
+                       CAST(AVG(s.total_memory) AS INTEGER), CAST(AVG(s.disk_usage) AS INTEGER), CAST(AVG(s.total_disk) AS INTEGER),
+                       CAST(AVG(s.network_rx) AS INTEGER), CAST(AVG(s.network_tx) AS INTEGER), AVG(s.active_sessions)
+                FROM metrics_samples AS s
+                WHERE s.resolution = ?2
+                  AND s.sampled_at >= COALESCE((SELECT MAX(r.sampled_at) FROM metrics_samples AS r WHERE r.resolution = ?1 AND r.daemon_id = s.daemon_id) + ?1, 0)
+                  AND s.sampled_at < ?3
+                GROUP BY s.daemon_id, bucket
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .bind(end)
+            .execute(self.db.pool())
+            .await?;
+
+            query(
+                r#"
+                INSERT INTO session_metrics_samples (daemon_id, session_id, resolution, sampled_at, cpu_usage, memory_usage, network_rx, network_tx)
+                SELECT s.daemon_id, s.session_id, ?1, (s.sampled_at / ?1) * ?1 AS bucket, AVG(s.cpu_usage), CAST(AVG(s.memory_usage) AS INTEGER),
+                       CAST(AVG(s.network_rx) AS INTEGER), CAST(AVG(s.network_tx) AS INTEGER)
+                FROM session_metrics_samples AS s
+                WHERE s.resolution = ?2
+                  AND s.sampled_at >= COALESCE((SELECT MAX(r.sampled_at) FROM session_metrics_samples AS r WHERE r.resolution = ?1 AND r.daemon_id = s.daemon_id) + ?1, 0)
+                  AND s.sampled_at < ?3
+                GROUP BY s.daemon_id, s.session_id, bucket
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .bind(end)
+            .execute(self.db.pool())
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes samples that have outlived their tier's retention. Returns
+    /// the number of rows removed.
+    pub async fn purge(&self, now: DateTime<Utc>, retention_days: i64) -> Result<u64> {
+        let mut removed = 0;
+
+        for tier in &TIERS {
+            let retention = tier.retention_secs.unwrap_or(retention_days * DAY);
+            let cutoff = now.timestamp() - retention;
+
+            for table in ["metrics_samples", "session_metrics_samples"] {
+                removed += query(&format!(
+                    "DELETE FROM {table} WHERE resolution = ? AND sampled_at < ?"
+                ))
+                .bind(tier.resolution)
+                .bind(cutoff)
+                .execute(self.db.pool())
+                .await?
+                .rows_affected();
+            }
+        }
+
+        Ok(removed)
+    }
+
+    pub async fn system_series(
+        &self,
+        daemon_id: Uuid,
+        range: &MetricsRangeQuery,
+    ) -> Result<MetricsSeries<SystemMetricsPoint>> {
+        let window = Window::resolve(range, Utc::now())?;
+
+        let rows = query_as::<_, SystemMetricsRow>(
+            r#"
+            SELECT (sampled_at / ?1) * ?1 AS bucket, AVG(cpu_usage) AS cpu_usage,
+                   CAST(AVG(memory_usage) AS INTEGER) AS memory_usage, CAST(AVG(total_memory) AS INTEGER) AS total_memory,
+                   CAST(AVG(disk_usage) AS INTEGER) AS disk_usage, CAST(AVG(total_disk) AS INTEGER) AS total_disk,
+                   CAST(AVG(network_rx) AS INTEGER) AS network_rx, CAST(AVG(network_tx) AS INTEGER) AS network_tx,
+                   AVG(active_sessions) AS active_sessions
+            FROM metrics_samples
+            WHERE daemon_id = ?5 AND resolution = ?2 AND sampled_at >= ?3 AND sampled_at <= ?4
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+        )
+        .bind(window.step)
+        .bind(window.resolution)
+        .bind(window.from)
+        .bind(window.to)
+        .bind(daemon_id.to_string())
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let points = rows
+            .into_iter()
+            .map(|row| SystemMetricsPoint {
+                timestamp: timestamp(row.bucket),
+                cpu_usage: row.cpu_usage,
+                memory_usage: row.memory_usage.max(0) as u64,
+                total_memory: row.total_memory.max(0) as u64,
+                disk_usage: row.disk_usage.max(0) as u64,
+                total_disk: row.total_disk.max(0) as u64,
+                network_rx: row.network_rx.max(0) as u64,
+                network_tx: row.network_tx.max(0) as u64,
+                active_sessions: row.active_sessions,
+            })
+            .collect();
+
+        Ok(window.series(points))
+    }
+
+    pub async fn session_series(
+        &self,
+        daemon_id: Uuid,
+        session_id: Uuid,
+        range: &MetricsRangeQuery,
+    ) -> Result<MetricsSeries<SessionMetricsPoint>> {
+        let window = Window::resolve(range, Utc::now())?;
+
+        let rows = query_as::<_, SessionMetricsRow>(
+            r#"
+            SELECT (sampled_at / ?1) * ?1 AS bucket, AVG(cpu_usage) AS cpu_usage,
+                   CAST(AVG(memory_usage) AS INTEGER) AS memory_usage,
+                   CAST(AVG(network_rx) AS INTEGER) AS network_rx, CAST(AVG(network_tx) AS INTEGER) AS network_tx
+            FROM session_metrics_samples
+            WHERE daemon_id = ?6 AND session_id = ?5 AND resolution = ?2 AND sampled_at >= ?3 AND sampled_at <= ?4
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+        )
+        .bind(window.step)
+        .bind(window.resolution)
+        .bind(window.from)
+        .bind(window.to)
+        .bind(session_id.to_string())
+        .bind(daemon_id.to_string())
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let points = rows
+            .into_iter()
+            .map(|row| SessionMetricsPoint {
+                timestamp: timestamp(row.bucket),
+                cpu_usage: row.cpu_usage,
+                memory_usage: row.memory_usage.max(0) as u64,
+                network_rx: row.network_rx.max(0) as u64,
+                network_tx: row.network_tx.max(0) as u64,
+            })
+            .collect();
+
+        Ok(window.series(points))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SessionMetrics;
+    use sqlx::query_scalar;
+
+    fn now() -> DateTime<Utc> {
+        // On an hour boundary, so every bucket before it is complete
+        "2026-01-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn ago(secs: i64) -> Option<DateTime<Utc>> {
+        Some(now() - Duration::seconds(secs))
+    }
+
+    fn window(from: Option<DateTime<Utc>>, step: Option<i64>) -> Result<Window> {
+        let range = MetricsRangeQuery {
+            from,
+            to: None,
+            step,
+        };
+        Window::resolve(&range, now())
+    }
+
+    #[test]
+    fn picks_the_coarsest_tier_covering_the_range() {
+        // Recent data at a fine step comes from the raw samples
+        let recent = window(None, None).unwrap();
+        assert_eq!((recent.resolution, recent.step), (RAW, 12));
+
+        // A step of an hour over the last day reads the hourly rollups
+        let day = window(ago(DAY), Some(HOUR)).unwrap();
+        assert_eq!(day.resolution, HOUR);
+
+        // Raw samples are gone after two days, so the 5 minute tier is used
+        // and the step widened to it
+        let week = window(ago(7 * DAY), Some(60)).unwrap();
+        assert_eq!((week.resolution, week.step), (5 * MINUTE, 5 * MINUTE));
+        let week = window(ago(7 * DAY), None).unwrap();
+        assert_eq!((week.resolution, week.step), (5 * MINUTE, 2016));
+
+        // Older than any rollup tier: only the hourly one is left
+        let month = window(ago(30 * DAY), Some(60)).unwrap();
+        assert_eq!((month.resolution, month.step), (HOUR, HOUR));
+    }
+
+    #[test]
+    fn refuses_bad_ranges() {
+        let range = MetricsRangeQuery {
+            from: Some(now()),
+            to: ago(HOUR),
+            step: None,
+        };
+        assert!(Window::resolve(&range, now()).is_err());
+        assert!(window(ago(HOUR), Some(0)).is_err());
+        assert!(window(ago(DAY), Some(1)).is_err());
+    }
+
+    async fn service() -> MetricsHistoryService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        MetricsHistoryService::new(db)
+    }
+
+    async fn record(history: &MetricsHistoryService, daemon: Uuid, at: i64, cpu: f64) {
+        let system = SystemMetrics {
+            cpu_usage: cpu,
+            memory_usage: 100,
+            total_memory: 1000,
+            disk_usage: 10,
+            total_disk: 100,
+        };
+        let session = Session {
+            id: Uuid::nil(),
+            application_id: "calc".to_string(),
+            user_id: None,
+            started_at: now(),
+            last_activity: now(),
+            status: SessionStatus::Active,
+            metrics: SessionMetrics {
+                cpu_usage: cpu,
+                memory_usage: 10,
+                network_rx: 0,
+                network_tx: 0,
+            },
+        };
+        history
+            .record(daemon, timestamp(at), &system, &[session])
+            .await
+            .unwrap();
+    }
+
+    /// `(sampled_at, cpu_usage)` of the system rows at `resolution`.
+    async fn rows(history: &MetricsHistoryService, resolution: i64) -> Vec<(i64, f64)> {
+        query_as(
+            "SELECT sampled_at, cpu_usage FROM metrics_samples WHERE resolution = ? ORDER BY sampled_at",
+        )
+        .bind(resolution)
+        .fetch_all(history.db.pool())
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn downsamples_complete_buckets_once() {
+        let history = service().await;
+        let daemon = Uuid::new_v4();
+        let end = now().timestamp();
+
+        // Two samples in each of the last two 5 minute buckets
+        for (offset, cpu) in [(600, 10.0), (420, 20.0), (300, 30.0), (60, 50.0)] {
+            record(&history, daemon, end - offset, cpu).await;
+        }
+        // Falls into the bucket still in progress
+        record(&history, daemon, end + 10, 99.0).await;
+
+        history.downsample(now()).await.unwrap();
+        assert_eq!(
+            rows(&history, 5 * MINUTE).await,
+            [(end - 600, 15.0), (end - 300, 40.0)]
+        );
+        assert_eq!(rows(&history, HOUR).await, [(end - HOUR, 27.5)]);
+
+        let sessions: i64 =
+            query_scalar("SELECT COUNT(*) FROM session_metrics_samples WHERE resolution = ?")
+                .bind(5 * MINUTE)
+                .fetch_one(history.db.pool())
+                .await
+                .unwrap();
+        assert_eq!(sessions, 2);
+
+        // Running again adds nothing
+        history.downsample(now()).await.unwrap();
+        assert_eq!(rows(&history, 5 * MINUTE).await.len(), 2);
+        assert_eq!(rows(&history, HOUR).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn downsamples_each_daemon_from_its_own_rollups() {
+        let history = service().await;
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let end = now().timestamp();
+
+        record(&history, first, end - 60, 10.0).await;
+        history.downsample(now()).await.unwrap();
+        // A slow daemon's sample lands after the bucket was rolled up
+        record(&history, second, end - 30, 30.0).await;
+        history.downsample(now()).await.unwrap();
+
+        for table in ["metrics_samples", "session_metrics_samples"] {
+            let rollups: Vec<String> = query_scalar(&format!(
+                "SELECT daemon_id FROM {table} WHERE resolution = ? ORDER BY daemon_id"
+            ))
+            .bind(5 * MINUTE)
+            .fetch_all(history.db.pool())
+            .await
+            .unwrap();
+            let mut expected = vec![first.to_string(), second.to_string()];
+            expected.sort();
+            assert_eq!(rollups, expected, "{table}");
+        }
+    }
+
+    #[tokio::test]
+    async fn purges_each_tier_after_its_retention() {
+        let history = service().await;
+        let daemon = Uuid::new_v4();
+        let end = now().timestamp();
+
+        for age in [DAY, 3 * DAY, 20 * DAY, 40 * DAY] {
+            record(&history, daemon, end - age, 1.0).await;
+        }
+        // Roll everything up, as the recorder does over time
+        history.downsample(now()).await.unwrap();
+        assert_eq!(rows(&history, HOUR).await.len(), 4);
+
+        let removed = history.purge(now(), 30).await.unwrap();
+        let ages = |rows: Vec<(i64, f64)>| -> Vec<i64> {
+            rows.into_iter().map(|(at, _)| (end - at) / DAY).collect()
+        };
+        assert_eq!(ages(rows(&history, RAW).await), [1]);
+        assert_eq!(ages(rows(&history, 5 * MINUTE).await), [3, 1]);
+        assert_eq!(ages(rows(&history, HOUR).await), [20, 3, 1]);
+        // The system and session rows of 3 raw samples, 2 rollups and 1
+        // hourly row
+        assert_eq!(removed, 2 * (3 + 2 + 1));
+    }
+}
@@ -1,6 +1,12 @@
-pub mod application;
+pub mod api_key;
+pub mod audit;
 pub mod auth;
-pub mod metrics;
+pub mod daemon;
+pub mod launch_history;
+pub mod login_throttle;
+pub mod metrics_history;
+pub mod password;
+pub mod permission;
 pub mod rcpdaemon;
-pub mod session;
+pub mod totp;
 pub mod user;
@@ -0,0 +1,303 @@
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{query, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    config::{PasswordConfig, MAX_PASSWORD_BYTES},
+    db::Database,
+    error::{AppError, FieldError, Result},
+    models::{PasswordResetToken, User},
+    services::{auth::hash_token, user::UserService},
+};
+
+/// Password changes and resets, checked against [`PasswordConfig`]. Setting a
+/// password revokes every session of the user.
+pub struct PasswordService {
+    db: Database,
+    policy: PasswordConfig,
+}
+
+impl PasswordService {
+    pub fn new(db: Database, policy: &PasswordConfig) -> Self {
+        Self {
+            db,
+            policy: policy.clone(),
+        }
+    }
+
+    /// Replaces the password of `user`. The caller has checked the current
+    /// password.
+    pub async fn change(&self, user: &User, new_password: &str, now: DateTime<Utc>) -> Result<()> {
+        check_policy(&self.policy, "new_password", new_password, &user.username)?;
+        if verify_password(user, new_password)? {
+            return Err(AppError::InvalidFields(vec![FieldError::new(
+                "new_password",
+                "Must differ from the current password",
+            )]));
+        }
+        self.set_password(user.id, new_password, now).await
+    }
+
+    /// Issues a reset token for `user_id`, replacing any earlier one.
+    pub async fn create_reset_token(
+        &self,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<PasswordResetToken> {
+        query("DELETE FROM password_reset_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.db.pool())
+            .await?;
+
+        let reset_token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let expires_at = now + Duration::minutes(self.policy.reset_token_ttl_minutes);
+        // REPLACE drops the user's previous token through the UNIQUE user_id
+        query(
+            "INSERT OR REPLACE INTO password_reset_tokens (token_hash, user_id, expires_at, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(hash_token(&reset_token))
+        .bind(user_id.to_string())
+        .bind(expires_at)
+        .bind(now)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(PasswordResetToken {
+            reset_token,
+            expires_at,
+        })
+    }
+
+    /// Sets a new password through a reset token and returns the user it
+    /// belonged to. A password the policy refuses leaves the token usable.
+    pub async fn reset(
+        &self,
+        reset_token: &str,
+        new_password: &str,
+        now: DateTime<Utc>,
+    ) -> Result<User> {
+        let invalid = || AppError::Auth("Invalid or expired reset token".to_string());
+
+        let user_id: String = query_scalar(
+            "SELECT user_id FROM password_reset_tokens WHERE token_hash = ? AND expires_at > ?",
+        )
+        .bind(hash_token(reset_token))
+        .bind(now)
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(invalid)?;
+        let user_id = Uuid::parse_str(&user_id).map_err(|_| invalid())?;
+
+        let user = UserService::new(self.db.clone()).get_user(user_id).await?;
+        check_policy(&self.policy, "new_password", new_password, &user.username)?;
+
+        // Spend the token first; of two requests racing with it only one wins
+        let spent =
+            query("DELETE FROM password_reset_tokens WHERE token_hash = ? AND expires_at > ?")
+                .bind(hash_token(reset_token))
+                .bind(now)
+                .execute(self.db.pool())
+                .await?;
+        if spent.rows_affected() == 0 {
+            return Err(invalid());
+        }
+
+        self.set_password(user.id, new_password, now).await?;
+        Ok(user)
+    }
+
+    /// Stores the new hash and revokes the user's sessions: refresh tokens
+    /// directly, access tokens through `password_changed_at`, see
+    /// `AuthService::is_token_revoked`.
+    async fn set_password(&self, user_id: Uuid, password: &str, now: DateTime<Utc>) -> Result<()> {
+        let password_hash = hash_password(password)?;
+
+        let mut tx = self.db.pool().begin().await?;
+        let updated = query(
+            "UPDATE users SET password_hash = ?, password_changed_at = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(&password_hash)
+        .bind(now)
+        .bind(now.to_rfc3339())
+        .bind(user_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        query("UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+            .bind(now)
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        query("DELETE FROM password_reset_tokens WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String> {
+    hash(password, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to hash password: {}", e)))
+}
+
+pub fn verify_password(user: &User, password: &str) -> Result<bool> {
+    verify(password, &user.password_hash)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Password verification failed: {}", e)))
+}
+
+/// Checks `password` against the policy, reporting every rule it breaks
+/// under `field`.
+pub fn check_policy(
+    policy: &PasswordConfig,
+    field: &str,
+    password: &str,
+    username: &str,
+) -> Result<()> {
+    let mut problems = Vec::new();
+
+    if password.chars().count() < policy.min_length {
+        problems.push(format!(
+            "Must be at least {} characters long",
+            policy.min_length
+        ));
+    }
+    if password.len() > MAX_PASSWORD_BYTES {
+        problems.push(format!("Must be at most {} bytes long", MAX_PASSWORD_BYTES));
+    }
+    for (required, present, what) in [
+        (
+            policy.require_uppercase,
+            password.chars().any(char::is_uppercase),
+            "an uppercase letter",
+        ),
+        (
+            policy.require_lowercase,
+            password.chars().any(char::is_lowercase),
+            "a lowercase letter",
+        ),
+        (
+            policy.require_digit,
+            password.chars().any(|c| c.is_ascii_digit()),
+            "a digit",
+        ),
+        (
+            policy.require_symbol,
+            password.chars().any(|c| !c.is_alphanumeric()),
+            "a symbol",
+        ),
+    ] {
+        if required && !present {
+            problems.push(format!("Must contain {}", what));
+        }
+    }
+    if policy.reject_username
+        && !username.is_empty()
+        && password.to_lowercase().contains(&username.to_lowercase())
+    {
+        problems.push("Must not contain the username".to_string());
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidFields(
+            problems
+                .into_iter()
+                .map(|message| FieldError::new(field, message))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::LoginRequest, services::auth::AuthService};
+
+    const ADMIN_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn service() -> PasswordService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        PasswordService::new(db, &PasswordConfig::default())
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-01-01T12:00:00Z".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn reset_tokens_work_once() {
+        let passwords = service().await;
+        let token = passwords
+            .create_reset_token(ADMIN_ID.parse().unwrap(), now())
+            .await
+            .unwrap();
+        assert_eq!(token.expires_at, now() + Duration::minutes(60));
+
+        // A refused password leaves the token usable
+        assert!(matches!(
+            passwords.reset(&token.reset_token, "short", now()).await,
+            Err(AppError::InvalidFields(_))
+        ));
+
+        let user = passwords
+            .reset(&token.reset_token, "a much longer secret", now())
+            .await
+            .unwrap();
+        assert_eq!(user.username, "admin");
+        assert!(matches!(
+            passwords
+                .reset(&token.reset_token, "another long secret", now())
+                .await,
+            Err(AppError::Auth(_))
+        ));
+
+        let login = LoginRequest {
+            username: "admin".to_string(),
+            password: "a much longer secret".to_string(),
+        };
+        assert!(AuthService::new(passwords.db.clone())
+            .authenticate(&login)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn reset_tokens_expire() {
+        let passwords = service().await;
+        let token = passwords
+            .create_reset_token(ADMIN_ID.parse().unwrap(), now())
+            .await
+            .unwrap();
+
+        let later = now() + Duration::minutes(60);
+        assert!(matches!(
+            passwords
+                .reset(&token.reset_token, "a much longer secret", later)
+                .await,
+            Err(AppError::Auth(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_new_reset_token_replaces_the_previous_one() {
+        let passwords = service().await;
+        let admin = ADMIN_ID.parse().unwrap();
+        let first = passwords.create_reset_token(admin, now()).await.unwrap();
+        passwords.create_reset_token(admin, now()).await.unwrap();
+
+        assert!(matches!(
+            passwords
+                .reset(&first.reset_token, "a much longer secret", now())
+                .await,
+            Err(AppError::Auth(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use sqlx::{query, query_scalar};
+use tracing::warn;
+
+use crate::{
+    db::Database,
+    error::{AppError, Result},
+    models::{Permission, RolePermissions, UserRole},
+};
+
+pub struct PermissionService {
+    db: Database,
+}
+
+impl PermissionService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn role_permissions(&self, role: &UserRole) -> Result<HashSet<Permission>> {
+        let rows: Vec<String> =
+            query_scalar("SELECT permission FROM role_permissions WHERE role = ?")
+                .bind(role.to_string())
+                .fetch_all(self.db.pool())
+                .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|p| match Permission::from_str(p) {
+                Ok(permission) => Some(permission),
+                Err(e) => {
+                    // Most likely written by a newer build; ignore rather than fail every request
+                    warn!("Ignoring permission granted to {}: {}", role, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    pub async fn list_roles(&self) -> Result<Vec<RolePermissions>> {
+        let mut roles = Vec::new();
+        for role in [UserRole::Admin, UserRole::Operator, UserRole::Viewer] {
+            let mut permissions: Vec<Permission> =
+                self.role_permissions(&role).await?.into_iter().collect();
+            permissions.sort_by_key(|p| p.as_str());
+            let require_totp = self.requires_totp(&role).await?;
+            roles.push(RolePermissions {
+                role,
+                permissions,
+                require_totp,
+            });
+        }
+        Ok(roles)
+    }
+
+    /// Whether members of `role` must use two-factor authentication.
+    pub async fn requires_totp(&self, role: &UserRole) -> Result<bool> {
+        let required: Option<bool> =
+            query_scalar("SELECT require_totp FROM role_settings WHERE role = ?")
+                .bind(role.to_string())
+                .fetch_optional(self.db.pool())
+                .await?;
+        Ok(required.unwrap_or(false))
+    }
+
+    pub async fn set_requires_totp(&self, role: &UserRole, required: bool) -> Result<()> {
+        query(
+            "INSERT INTO role_settings (role, require_totp) VALUES (?, ?) ON CONFLICT (role) DO UPDATE SET require_totp = excluded.require_totp",
+        )
+        .bind(role.to_string())
+        .bind(required)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the permission set of a role.
+    pub async fn set_role_permissions(
+        &self,
+        role: &UserRole,
+        permissions: &[Permission],
+    ) -> Result<()> {
+        // Otherwise nobody could ever grant permissions again
+        if *role == UserRole::Admin && !permissions.contains(&Permission::RolesManage) {
+            return Err(AppError::Validation(format!(
+                "The Admin role must keep the '{}' permission",
+                Permission::RolesManage
+            )));
+        }
+
+        let mut tx = self.db.pool().begin().await?;
+
+        query("DELETE FROM role_permissions WHERE role = ?")
+            .bind(role.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        for permission in permissions.iter().collect::<HashSet<_>>() {
+            query("INSERT INTO role_permissions (role, permission) VALUES (?, ?)")
+                .bind(role.to_string())
+                .bind(permission.as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -1,33 +1,171 @@
+use crate::config::Config;
 use crate::error::{AppError, Result};
-use crate::models::{Application, CreateApplication, ServerStatus, Session, SystemMetrics};
-use reqwest::{Client, StatusCode};
-use tracing::{error, info};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::warn;
 use uuid::Uuid;
 
+use self::api::{
+    Application, CreateApplication, DaemonLogEntry, LaunchRequest, LogsQuery, PathExists,
+    PathQuery, ServerStatus, Session, SessionMetrics, SystemMetrics,
+};
+
+/// The rcpdaemon HTTP API (its `api` feature): routes and wire types. The
+/// client below and the stub daemon in its contract tests are both built
+/// from this module, so a change to the daemon's API is made here or the
+/// tests fail. The daemon crate is not a dependency of the backend, so the
+/// wire types are declared here rather than imported from it.
+pub mod api {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Serialize};
+    use std::fmt::Display;
+
+    pub use crate::models::{
+        Application, CreateApplication, ServerStatus, Session, SessionMetrics, SystemMetrics,
+    };
+
+    // Route templates, in axum syntax; `:id` is filled in by [`path`]
+    pub const STATUS: &str = "/v1/status";
+    pub const HEALTH: &str = "/v1/health";
+    pub const CONFIG: &str = "/v1/config";
+    pub const SERVER_RESTART: &str = "/v1/server/restart";
+    pub const SYSTEM_METRICS: &str = "/v1/system/metrics";
+    pub const PERFORMANCE_METRICS: &str = "/v1/system/performance";
+    pub const SESSIONS: &str = "/v1/sessions";
+    pub const SESSION: &str = "/v1/sessions/:id";
+    pub const SESSION_METRICS: &str = "/v1/sessions/:id/metrics";
+    pub const APPS: &str = "/v1/apps";
+    pub const APP: &str = "/v1/apps/:id";
+    pub const APP_LAUNCH: &str = "/v1/apps/:id/launch";
+    pub const APP_STOP: &str = "/v1/apps/:id/stop";
+    pub const LOGS: &str = "/v1/logs";
+    pub const FS_EXISTS: &str = "/v1/fs/exists";
+
+    pub fn path(template: &str, id: impl Display) -> String {
+        template.replace(":id", &id.to_string())
+    }
+
+    /// Body of `POST` [`APP_LAUNCH`].
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct LaunchRequest {
+        pub arguments: Vec<String>,
+    }
+
+    /// Query of `GET` [`FS_EXISTS`], encoded by [`fs_exists`].
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PathQuery {
+        pub path: String,
+    }
+
+    /// Response of `GET` [`FS_EXISTS`].
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PathExists {
+        pub exists: bool,
+    }
+
+    /// Query of `GET` [`LOGS`], encoded by [`logs`]. Without `since` the
+    /// daemon sends every line it still has.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct LogsQuery {
+        pub since: Option<DateTime<Utc>>,
+    }
+
+    /// One line of `GET` [`LOGS`], oldest first.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct DaemonLogEntry {
+        pub timestamp: DateTime<Utc>,
+        pub level: String,
+        #[serde(default)]
+        pub target: Option<String>,
+        pub message: String,
+        #[serde(default)]
+        pub fields: serde_json::Map<String, serde_json::Value>,
+    }
+
+    pub fn fs_exists(query: &PathQuery) -> String {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("path", &query.path)
+            .finish();
+        format!("{}?{}", FS_EXISTS, query)
+    }
+
+    pub fn logs(query: &LogsQuery) -> String {
+        match query.since {
+            Some(since) => format!(
+                "{}?since={}",
+                LOGS,
+                since.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ),
+            None => LOGS.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DaemonClientOptions {
+    /// Total time allowed for a single HTTP request
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Extra attempts for idempotent calls that fail with a transport error
+    /// or a 502/503/504 from the daemon
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further attempt
+    pub retry_backoff: Duration,
+    /// Bearer token sent with every request
+    pub auth_token: Option<String>,
+}
+
+impl Default for DaemonClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(3),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(200),
+            auth_token: None,
+        }
+    }
+}
+
+impl From<&Config> for DaemonClientOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.rcpdaemon.timeout_secs),
+            connect_timeout: Duration::from_secs(config.rcpdaemon.connect_timeout_secs),
+            max_retries: config.rcpdaemon.max_retries,
+            auth_token: config.rcpdaemon.token.clone(),
+            ..Self::default()
+        }
+    }
+}
+
 pub struct RcpDaemonClient {
     client: Client,
     base_url: String,
     auth_token: Option<String>,
+    max_retries: u32,
+    retry_backoff: Duration,
 }
 
 impl RcpDaemonClient {
-    pub async fn new(base_url: &str) -> Result<Self> {
-        let rcp_client = Self {
-            client: Client::new(),
-            base_url: base_url.to_string(),
-            auth_token: None,
-        };
-
-        // Test connection to rcpdaemon
-        match rcp_client.get_status().await {
-            Ok(_) => info!("Successfully connected to RCP daemon at {}", base_url),
-            Err(e) => {
-                error!("Failed to connect to RCP daemon: {}", e);
-                // Continue anyway, as the daemon might become available later
-            }
-        }
-
-        Ok(rcp_client)
+    /// Does not contact the daemon; the fleet health poller reports whether
+    /// it can be reached.
+    pub fn new(base_url: &str, options: DaemonClientOptions) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout)
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_token: options.auth_token,
+            max_retries: options.max_retries,
+            retry_backoff: options.retry_backoff,
+        })
     }
 
     pub fn with_auth_token(mut self, token: Option<String>) -> Self {
@@ -35,157 +173,248 @@ impl RcpDaemonClient {
         self
     }
 
-    // Server Management
-
-    pub async fn get_status(&self) -> Result<ServerStatus> {
-        let response = self
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
             .client
-            .get(&format!("{}/v1/status", self.base_url))
-            .send()
-            .await?;
+            .request(method, format!("{}{}", self.base_url, path));
 
-        if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get server status: HTTP {}",
-                response.status()
-            )));
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
         }
+    }
 
-        let status = response.json::<ServerStatus>().await?;
-        Ok(status)
+    async fn send(&self, method: Method, path: &str) -> Result<Response> {
+        self.send_with(method, path, None::<&()>).await
     }
 
-    // Session Management
+    async fn send_json<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<Response> {
+        self.send_with(method, path, Some(body)).await
+    }
 
-    pub async fn get_sessions(&self) -> Result<Vec<Session>> {
-        let response = self
-            .client
-            .get(&format!("{}/v1/sessions", self.base_url))
-            .send()
-            .await?;
+    /// Sends a request, attaching the auth token. Idempotent methods are
+    /// retried with exponential backoff while the daemon is unreachable or
+    /// overloaded; POSTs are sent exactly once.
+    async fn send_with<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<Response> {
+        let idempotent = matches!(
+            method,
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+        );
+        let max_retries = if idempotent { self.max_retries } else { 0 };
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self.request(method.clone(), path);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
 
-        if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get sessions: HTTP {}",
-                response.status()
-            )));
+            let result = request.send().await;
+            let retryable = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+
+            if !retryable || attempt >= max_retries {
+                return result.map_err(|e| self.unreachable(e));
+            }
+
+            let delay = self.retry_backoff * 2u32.saturating_pow(attempt);
+            warn!(
+                "RCP daemon request {} {} failed, retrying in {:?}",
+                method, path, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
+    }
 
-        let sessions = response.json::<Vec<Session>>().await?;
-        Ok(sessions)
+    /// Transport failures mean the daemon is down or unreachable, which the
+    /// API reports as a bad gateway rather than an internal error.
+    fn unreachable(&self, err: reqwest::Error) -> AppError {
+        AppError::DaemonUnreachable(format!("RCP daemon at {}: {}", self.base_url, err))
     }
 
-    pub async fn get_session(&self, session_id: Uuid) -> Result<Session> {
-        let response = self
-            .client
-            .get(&format!("{}/v1/sessions/{}", self.base_url, session_id))
-            .send()
-            .await?;
+    async fn parse<T: DeserializeOwned>(response: Response) -> Result<T> {
+        response
+            .json::<T>()
+            .await
+            .map_err(|e| AppError::RcpDaemon(format!("Invalid response from RCP daemon: {}", e)))
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            return Err(AppError::NotFound(format!(
-                "Session {} not found",
-                session_id
-            )));
-        } else if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get session: HTTP {}",
-                response.status()
-            )));
+    /// Fails unless the daemon answered with one of `expected`, mapping 404 to
+    /// [`AppError::NotFound`] with `not_found` as the message.
+    fn check(
+        response: &Response,
+        expected: &[StatusCode],
+        action: &str,
+        not_found: &str,
+    ) -> Result<()> {
+        let status = response.status();
+        if expected.contains(&status) {
+            Ok(())
+        } else if status == StatusCode::NOT_FOUND {
+            Err(AppError::NotFound(not_found.to_string()))
+        } else {
+            Err(AppError::RcpDaemon(format!(
+                "Failed to {}: HTTP {}",
+                action, status
+            )))
         }
+    }
+
+    // Server Management
 
-        let session = response.json::<Session>().await?;
-        Ok(session)
+    pub async fn get_status(&self) -> Result<ServerStatus> {
+        let response = self.send(Method::GET, api::STATUS).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get server status",
+            "Status endpoint not found",
+        )?;
+        Self::parse(response).await
     }
 
-    pub async fn close_session(&self, session_id: Uuid) -> Result<()> {
-        let response = self
-            .client
-            .delete(&format!("{}/v1/sessions/{}", self.base_url, session_id))
-            .send()
-            .await?;
+    pub async fn get_health(&self) -> Result<Value> {
+        let response = self.send(Method::GET, api::HEALTH).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get daemon health",
+            "Health endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            return Err(AppError::NotFound(format!(
-                "Session {} not found",
-                session_id
-            )));
-        } else if response.status() != StatusCode::OK && response.status() != StatusCode::NO_CONTENT
-        {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to close session: HTTP {}",
-                response.status()
-            )));
-        }
+    pub async fn restart_server(&self) -> Result<Value> {
+        let response = self.send(Method::POST, api::SERVER_RESTART).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK, StatusCode::ACCEPTED],
+            "restart server",
+            "Restart endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
 
-        Ok(())
+    pub async fn get_config(&self) -> Result<Value> {
+        let response = self.send(Method::GET, api::CONFIG).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get server config",
+            "Config endpoint not found",
+        )?;
+        Self::parse(response).await
     }
 
-    // Application Management
+    // Session Management
 
-    pub async fn get_applications(&self) -> Result<Vec<Application>> {
+    pub async fn get_sessions(&self) -> Result<Vec<Session>> {
+        let response = self.send(Method::GET, api::SESSIONS).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get sessions",
+            "Sessions endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
+
+    pub async fn get_session(&self, session_id: Uuid) -> Result<Session> {
         let response = self
-            .client
-            .get(&format!("{}/v1/apps", self.base_url))
-            .send()
+            .send(Method::GET, &api::path(api::SESSION, session_id))
             .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get session",
+            &format!("Session {} not found", session_id),
+        )?;
+        Self::parse(response).await
+    }
 
-        if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get applications: HTTP {}",
-                response.status()
-            )));
-        }
-
-        let apps = response.json::<Vec<Application>>().await?;
-        Ok(apps)
+    pub async fn get_session_metrics(&self, session_id: Uuid) -> Result<SessionMetrics> {
+        let response = self
+            .send(Method::GET, &api::path(api::SESSION_METRICS, session_id))
+            .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get session metrics",
+            &format!("Session {} not found", session_id),
+        )?;
+        Self::parse(response).await
     }
 
-    pub async fn get_application(&self, app_id: &str) -> Result<Application> {
+    pub async fn close_session(&self, session_id: Uuid) -> Result<()> {
         let response = self
-            .client
-            .get(&format!("{}/v1/apps/{}", self.base_url, app_id))
-            .send()
+            .send(Method::DELETE, &api::path(api::SESSION, session_id))
             .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK, StatusCode::NO_CONTENT],
+            "close session",
+            &format!("Session {} not found", session_id),
+        )
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            return Err(AppError::NotFound(format!(
-                "Application {} not found",
-                app_id
-            )));
-        } else if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get application: HTTP {}",
-                response.status()
-            )));
-        }
+    // Application Management
+
+    pub async fn get_applications(&self) -> Result<Vec<Application>> {
+        let response = self.send(Method::GET, api::APPS).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get applications",
+            "Applications endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
 
-        let app = response.json::<Application>().await?;
-        Ok(app)
+    pub async fn get_application(&self, app_id: &str) -> Result<Application> {
+        let response = self.send(Method::GET, &api::path(api::APP, app_id)).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get application",
+            &format!("Application {} not found", app_id),
+        )?;
+        Self::parse(response).await
     }
 
     pub async fn create_application(&self, app: CreateApplication) -> Result<Application> {
-        let response = self
-            .client
-            .post(&format!("{}/v1/apps", self.base_url))
-            .json(&app)
-            .send()
-            .await?;
+        let response = self.send_json(Method::POST, api::APPS, &app).await?;
 
         if response.status() == StatusCode::CONFLICT {
-            return Err(AppError::Validation(format!(
+            return Err(AppError::Conflict(format!(
                 "Application with name '{}' already exists",
                 app.name
             )));
-        } else if response.status() != StatusCode::CREATED && response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to create application: HTTP {}",
-                response.status()
-            )));
         }
-
-        let created_app = response.json::<Application>().await?;
-        Ok(created_app)
+        Self::check(
+            &response,
+            &[StatusCode::CREATED, StatusCode::OK],
+            "create application",
+            "Applications endpoint not found",
+        )?;
+        Self::parse(response).await
     }
 
     pub async fn update_application(
@@ -194,68 +423,381 @@ impl RcpDaemonClient {
         app: CreateApplication,
     ) -> Result<Application> {
         let response = self
-            .client
-            .put(&format!("{}/v1/apps/{}", self.base_url, app_id))
-            .json(&app)
-            .send()
+            .send_json(Method::PUT, &api::path(api::APP, app_id), &app)
             .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "update application",
+            &format!("Application {} not found", app_id),
+        )?;
+        Self::parse(response).await
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            return Err(AppError::NotFound(format!(
-                "Application {} not found",
-                app_id
-            )));
-        } else if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to update application: HTTP {}",
-                response.status()
-            )));
-        }
+    pub async fn delete_application(&self, app_id: &str) -> Result<()> {
+        let response = self
+            .send(Method::DELETE, &api::path(api::APP, app_id))
+            .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK, StatusCode::NO_CONTENT],
+            "delete application",
+            &format!("Application {} not found", app_id),
+        )
+    }
 
-        let updated_app = response.json::<Application>().await?;
-        Ok(updated_app)
+    pub async fn launch_application(&self, app_id: &str, arguments: &[String]) -> Result<Value> {
+        let body = LaunchRequest {
+            arguments: arguments.to_vec(),
+        };
+        let response = self
+            .send_json(Method::POST, &api::path(api::APP_LAUNCH, app_id), &body)
+            .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK, StatusCode::CREATED],
+            "launch application",
+            &format!("Application {} not found", app_id),
+        )?;
+        Self::parse(response).await
     }
 
-    pub async fn delete_application(&self, app_id: &str) -> Result<()> {
+    pub async fn stop_application(&self, app_id: &str) -> Result<Value> {
         let response = self
-            .client
-            .delete(&format!("{}/v1/apps/{}", self.base_url, app_id))
-            .send()
+            .send(Method::POST, &api::path(api::APP_STOP, app_id))
             .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "stop application",
+            &format!("Application {} not found", app_id),
+        )?;
+        Self::parse(response).await
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            return Err(AppError::NotFound(format!(
-                "Application {} not found",
-                app_id
-            )));
-        } else if response.status() != StatusCode::OK && response.status() != StatusCode::NO_CONTENT
-        {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to delete application: HTTP {}",
-                response.status()
-            )));
+    /// Whether `path` exists on the daemon host, or `None` when the daemon
+    /// is too old to say.
+    pub async fn path_exists(&self, path: &str) -> Result<Option<bool>> {
+        let response = self
+            .send(
+                Method::GET,
+                &api::fs_exists(&PathQuery {
+                    path: path.to_string(),
+                }),
+            )
+            .await?;
+        match Self::check(
+            &response,
+            &[StatusCode::OK],
+            "check application path",
+            "Path check endpoint not found",
+        ) {
+            Ok(()) => {}
+            Err(AppError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
         }
-
-        Ok(())
+        let result: PathExists = Self::parse(response).await?;
+        Ok(Some(result.exists))
     }
 
     // System monitoring
 
     pub async fn get_system_metrics(&self) -> Result<SystemMetrics> {
+        let response = self.send(Method::GET, api::SYSTEM_METRICS).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get system metrics",
+            "Metrics endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
+
+    pub async fn get_performance_metrics(&self) -> Result<Value> {
+        let response = self.send(Method::GET, api::PERFORMANCE_METRICS).await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get performance metrics",
+            "Metrics endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
+
+    /// Log lines recorded by the daemon, oldest first; only those after
+    /// `since` when given.
+    pub async fn get_logs(&self, since: Option<DateTime<Utc>>) -> Result<Vec<DaemonLogEntry>> {
         let response = self
-            .client
-            .get(&format!("{}/v1/system/metrics", self.base_url))
-            .send()
+            .send(Method::GET, &api::logs(&LogsQuery { since }))
             .await?;
+        Self::check(
+            &response,
+            &[StatusCode::OK],
+            "get daemon logs",
+            "Logs endpoint not found",
+        )?;
+        Self::parse(response).await
+    }
+}
 
-        if response.status() != StatusCode::OK {
-            return Err(AppError::RcpDaemon(format!(
-                "Failed to get system metrics: HTTP {}",
-                response.status()
-            )));
+/// Contract tests against a stub daemon serving the routes in [`api`].
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::{Path, Query, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+        Json, Router,
+    };
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+
+    fn options() -> DaemonClientOptions {
+        DaemonClientOptions {
+            timeout: Duration::from_millis(200),
+            connect_timeout: Duration::from_millis(200),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(20),
+            auth_token: None,
         }
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn metrics() -> SystemMetrics {
+        SystemMetrics {
+            cpu_usage: 12.5,
+            memory_usage: 512,
+            total_memory: 1024,
+            disk_usage: 10,
+            total_disk: 100,
+        }
+    }
+
+    fn status() -> ServerStatus {
+        ServerStatus {
+            version: "1.2.3".to_string(),
+            uptime: 60,
+            active_sessions: 2,
+            system_metrics: metrics(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_auth_token_and_parses_status() {
+        let router = Router::new().route(
+            api::STATUS,
+            get(|headers: HeaderMap| async move {
+                match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+                    Some("Bearer secret") => Ok(Json(status())),
+                    _ => Err(StatusCode::UNAUTHORIZED),
+                }
+            }),
+        );
+        let url = serve(router).await;
+
+        let client = RcpDaemonClient::new(&url, options())
+            .unwrap()
+            .with_auth_token(Some("secret".to_string()));
+        let status = client.get_status().await.unwrap();
+        assert_eq!(status.version, "1.2.3");
+        assert_eq!(status.active_sessions, 2);
+
+        let anonymous = RcpDaemonClient::new(&url, options()).unwrap();
+        assert!(matches!(
+            anonymous.get_status().await,
+            Err(AppError::RcpDaemon(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fills_in_route_parameters() {
+        let router = Router::new()
+            .route(
+                api::SESSION,
+                get(|Path(id): Path<Uuid>| async move {
+                    if id == Uuid::nil() {
+                        Err(StatusCode::NOT_FOUND)
+                    } else {
+                        Ok(StatusCode::NO_CONTENT)
+                    }
+                })
+                .delete(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route(
+                api::APP_LAUNCH,
+                post(
+                    |Path(id): Path<String>, Json(body): Json<LaunchRequest>| async move {
+                        Json(serde_json::json!({ "app": id, "arguments": body.arguments }))
+                    },
+                ),
+            );
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        client.close_session(Uuid::new_v4()).await.unwrap();
+        assert!(matches!(
+            client.get_session(Uuid::nil()).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        let launched = client
+            .launch_application("editor", &["--safe".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            launched,
+            serde_json::json!({ "app": "editor", "arguments": ["--safe"] })
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_calls_on_5xx_with_backoff() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let router = Router::new()
+            .route(
+                api::SYSTEM_METRICS,
+                get(|State(calls): State<Arc<AtomicU32>>| async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                        Err(StatusCode::SERVICE_UNAVAILABLE)
+                    } else {
+                        Ok(Json(metrics()))
+                    }
+                }),
+            )
+            .with_state(calls.clone());
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        let started = Instant::now();
+        let metrics = client.get_system_metrics().await.unwrap();
+        assert_eq!(metrics.total_memory, 1024);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        // 20ms, then 40ms
+        assert!(started.elapsed() >= Duration::from_millis(60));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_posts() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let router = Router::new()
+            .route(
+                api::APP_STOP,
+                post(|State(calls): State<Arc<AtomicU32>>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::SERVICE_UNAVAILABLE
+                }),
+            )
+            .with_state(calls.clone());
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        assert!(matches!(
+            client.stop_application("editor").await,
+            Err(AppError::RcpDaemon(_))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_timeouts_then_reports_unreachable() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let router = Router::new()
+            .route(
+                api::SESSIONS,
+                get(|State(calls): State<Arc<AtomicU32>>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Json(Vec::<Session>::new())
+                }),
+            )
+            .with_state(calls.clone());
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        assert!(matches!(
+            client.get_sessions().await,
+            Err(AppError::DaemonUnreachable(_))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn restarts_the_server() {
+        let router = Router::new().route(
+            api::SERVER_RESTART,
+            post(|| async {
+                (
+                    StatusCode::ACCEPTED,
+                    Json(serde_json::json!({ "restarting": true })),
+                )
+            }),
+        );
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        let result = client.restart_server().await.unwrap();
+        assert_eq!(result, serde_json::json!({ "restarting": true }));
+    }
+
+    #[tokio::test]
+    async fn checks_paths_on_the_daemon_host() {
+        let router = Router::new().route(
+            api::FS_EXISTS,
+            get(|Query(query): Query<api::PathQuery>| async move {
+                Json(PathExists {
+                    exists: query.path == "/opt/My Editor/editor",
+                })
+            }),
+        );
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        assert_eq!(
+            client.path_exists("/opt/My Editor/editor").await.unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            client.path_exists("/opt/missing").await.unwrap(),
+            Some(false)
+        );
+
+        // A daemon without the route cannot tell
+        let older = RcpDaemonClient::new(&serve(Router::new()).await, options()).unwrap();
+        assert_eq!(older.path_exists("/opt/missing").await.unwrap(), None);
+    }
 
-        let metrics = response.json::<SystemMetrics>().await?;
-        Ok(metrics)
+    #[tokio::test]
+    async fn fetches_logs_since_a_timestamp() {
+        let since: DateTime<Utc> = "2026-01-01T12:00:00.250Z".parse().unwrap();
+        let router = Router::new().route(
+            api::LOGS,
+            get(|Query(query): Query<api::LogsQuery>| async move {
+                let lines = [10, 20]
+                    .into_iter()
+                    .map(|secs| DaemonLogEntry {
+                        timestamp: "2026-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+                            + chrono::Duration::seconds(secs),
+                        level: "error".to_string(),
+                        target: None,
+                        message: format!("line at {}s", secs),
+                        fields: Default::default(),
+                    })
+                    .filter(|line| query.since.is_none_or(|since| line.timestamp > since))
+                    .collect::<Vec<_>>();
+                Json(lines)
+            }),
+        );
+        let client = RcpDaemonClient::new(&serve(router).await, options()).unwrap();
+
+        assert_eq!(client.get_logs(None).await.unwrap().len(), 2);
+        let later = since + chrono::Duration::seconds(15);
+        let lines = client.get_logs(Some(later)).await.unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].message, "line at 20s");
     }
 }
@@ -0,0 +1,494 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{query, query_as, query_scalar};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{AppError, Result},
+    models::{TotpEnrollment, TotpStatus, User, UserRole},
+    services::{auth::hash_token, permission::PermissionService},
+};
+
+/// RFC 6238 defaults, which is what authenticator apps assume
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+/// Codes from one step either side are accepted, for clock drift
+const TOTP_SKEW_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// How long a login may wait for its second factor
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Wrong codes allowed per challenge before the password must be re-entered
+const MAX_CHALLENGE_ATTEMPTS: i64 = 5;
+
+#[derive(Debug, sqlx::FromRow)]
+struct UserTotpDb {
+    secret: String,
+    enabled_at: Option<DateTime<Utc>>,
+    last_used_step: Option<i64>,
+}
+
+pub struct TotpService {
+    db: Database,
+    issuer: String,
+}
+
+impl TotpService {
+    pub fn new(db: Database, issuer: &str) -> Self {
+        Self {
+            db,
+            issuer: issuer.to_string(),
+        }
+    }
+
+    pub async fn is_enabled(&self, user_id: Uuid) -> Result<bool> {
+        Ok(self
+            .load(user_id)
+            .await?
+            .is_some_and(|totp| totp.enabled_at.is_some()))
+    }
+
+    pub async fn status(&self, user_id: Uuid, role: &UserRole) -> Result<TotpStatus> {
+        let enabled = self.is_enabled(user_id).await?;
+        let required = PermissionService::new(self.db.clone())
+            .requires_totp(role)
+            .await?;
+        let recovery_codes_remaining: i64 = query_scalar(
+            "SELECT COUNT(*) FROM totp_recovery_codes WHERE user_id = ? AND used_at IS NULL",
+        )
+        .bind(user_id.to_string())
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(TotpStatus {
+            enabled,
+            required,
+            recovery_codes_remaining,
+        })
+    }
+
+    /// True if `user`'s role requires two-factor authentication and the user
+    /// has not enabled it yet.
+    pub async fn enrollment_required(&self, user: &User) -> Result<bool> {
+        let required: Option<bool> = query_scalar(
+            r#"
+            SELECT require_totp AND NOT EXISTS (
+                SELECT 1 FROM user_totp WHERE user_id = ? AND enabled_at IS NOT NULL
+            )
+            FROM role_settings WHERE role = ?
+            "#,
+        )
+        .bind(user.id.to_string())
+        .bind(user.role.to_string())
+        .fetch_optional(self.db.pool())
+        .await?;
+        Ok(required.unwrap_or(false))
+    }
+
+    /// Generates a new secret for `user`, replacing any unconfirmed one.
+    pub async fn begin_enrollment(&self, user: &User) -> Result<TotpEnrollment> {
+        if self.is_enabled(user.id).await? {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+            unreachable!("to_encoded always returns Secret::Encoded");
+        };
+        let totp = self.totp(&secret, &user.username)?;
+
+        query(
+            r#"
+            INSERT INTO user_totp (user_id, secret, created_at) VALUES (?, ?, ?)
+            ON CONFLICT (user_id) DO UPDATE SET
+                secret = excluded.secret, enabled_at = NULL, last_used_step = NULL,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(user.id.to_string())
+        .bind(&secret)
+        .bind(Utc::now())
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    /// Activates the pending secret once the user proves they can generate
+    /// codes from it. Returns a first set of recovery codes.
+    pub async fn confirm_enrollment(
+        &self,
+        user: &User,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<String>> {
+        let pending = self
+            .load(user.id)
+            .await?
+            .filter(|totp| totp.enabled_at.is_none())
+            .ok_or_else(|| {
+                AppError::Validation("No two-factor enrollment is pending".to_string())
+            })?;
+
+        if !self.accept_code(user, &pending, code, now).await? {
+            return Err(AppError::Validation(
+                "Invalid verification code".to_string(),
+            ));
+        }
+
+        query("UPDATE user_totp SET enabled_at = ? WHERE user_id = ?")
+            .bind(now)
+            .bind(user.id.to_string())
+            .execute(self.db.pool())
+            .await?;
+
+        self.regenerate_recovery_codes(user.id).await
+    }
+
+    /// Checks a login code: an authenticator code, or else an unused
+    /// recovery code, which is then spent.
+    pub async fn verify(&self, user: &User, code: &str, now: DateTime<Utc>) -> Result<bool> {
+        let Some(totp) = self
+            .load(user.id)
+            .await?
+            .filter(|totp| totp.enabled_at.is_some())
+        else {
+            return Ok(false);
+        };
+
+        if self.accept_code(user, &totp, code, now).await? {
+            return Ok(true);
+        }
+
+        let spent = query(
+            "UPDATE totp_recovery_codes SET used_at = ? WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        )
+        .bind(now)
+        .bind(user.id.to_string())
+        .bind(hash_token(&normalize_recovery_code(code)))
+        .execute(self.db.pool())
+        .await?;
+        Ok(spent.rows_affected() > 0)
+    }
+
+    /// Replaces all recovery codes of the user with new ones.
+    pub async fn regenerate_recovery_codes(&self, user_id: Uuid) -> Result<Vec<String>> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let random = Uuid::new_v4().simple().to_string();
+                format!("{}-{}", &random[..5], &random[5..10])
+            })
+            .collect();
+
+        let mut tx = self.db.pool().begin().await?;
+        query("DELETE FROM totp_recovery_codes WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        for code in &codes {
+            query("INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES (?, ?)")
+                .bind(user_id.to_string())
+                .bind(hash_token(&normalize_recovery_code(code)))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// Removes the secret and recovery codes. Returns whether 2FA was enabled.
+    pub async fn disable(&self, user_id: Uuid) -> Result<bool> {
+        let was_enabled = self.is_enabled(user_id).await?;
+
+        let mut tx = self.db.pool().begin().await?;
+        query("DELETE FROM user_totp WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        query("DELETE FROM totp_recovery_codes WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(was_enabled)
+    }
+
+    /// Starts the second login step. Returns the challenge token and its
+    /// lifetime in seconds.
+    pub async fn create_challenge(
+        &self,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(String, i64)> {
+        query("DELETE FROM login_challenges WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.db.pool())
+            .await?;
+
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let ttl = Duration::minutes(CHALLENGE_TTL_MINUTES);
+        query("INSERT INTO login_challenges (token_hash, user_id, expires_at) VALUES (?, ?, ?)")
+            .bind(hash_token(&token))
+            .bind(user_id.to_string())
+            .bind(now + ttl)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok((token, ttl.num_seconds()))
+    }
+
+    /// Looks up a live challenge and counts an attempt against it. A
+    /// challenge that ran out of attempts is dropped.
+    pub async fn use_challenge(&self, token: &str, now: DateTime<Utc>) -> Result<Uuid> {
+        let invalid = || AppError::Auth("Invalid or expired login challenge".to_string());
+
+        let (user_id, attempts): (String, i64) = query_as(
+            r#"
+            UPDATE login_challenges SET attempts = attempts + 1
+            WHERE token_hash = ? AND expires_at > ?
+            RETURNING user_id, attempts
+            "#,
+        )
+        .bind(hash_token(token))
+        .bind(now)
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(invalid)?;
+
+        if attempts > MAX_CHALLENGE_ATTEMPTS {
+            self.finish_challenge(token).await?;
+            return Err(invalid());
+        }
+        Uuid::parse_str(&user_id).map_err(|_| invalid())
+    }
+
+    pub async fn finish_challenge(&self, token: &str) -> Result<()> {
+        query("DELETE FROM login_challenges WHERE token_hash = ?")
+            .bind(hash_token(token))
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn load(&self, user_id: Uuid) -> Result<Option<UserTotpDb>> {
+        Ok(query_as::<_, UserTotpDb>(
+            "SELECT secret, enabled_at, last_used_step FROM user_totp WHERE user_id = ?",
+        )
+        .bind(user_id.to_string())
+        .fetch_optional(self.db.pool())
+        .await?)
+    }
+
+    /// Accepts an authenticator code at most once: the step it matched is
+    /// remembered and codes from that step or earlier are refused.
+    async fn accept_code(
+        &self,
+        user: &User,
+        stored: &UserTotpDb,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        let totp = self.totp(&stored.secret, &user.username)?;
+
+        let current = now.timestamp().max(0) as u64 / TOTP_STEP_SECS;
+        let matched = (current.saturating_sub(TOTP_SKEW_STEPS)..=current + TOTP_SKEW_STEPS)
+            .filter(|step| stored.last_used_step.is_none_or(|last| *step as i64 > last))
+            .find(|step| totp.check(&code, step * TOTP_STEP_SECS));
+        let Some(step) = matched else {
+            return Ok(false);
+        };
+
+        // Two requests racing with the same code: only one moves the step
+        let updated = query(
+            "UPDATE user_totp SET last_used_step = ? WHERE user_id = ? AND (last_used_step IS NULL OR last_used_step < ?)",
+        )
+        .bind(step as i64)
+        .bind(user.id.to_string())
+        .bind(step as i64)
+        .execute(self.db.pool())
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    fn totp(&self, secret: &str, account: &str) -> Result<TOTP> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid TOTP secret: {:?}", e)))?;
+        // Skew is applied by `accept_code`, which needs to know the step
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            0,
+            TOTP_STEP_SECS,
+            secret,
+            Some(self.issuer.clone()),
+            account.to_string(),
+        )
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid TOTP parameters: {}", e)))
+    }
+}
+
+/// Recovery codes are accepted regardless of case and dashes.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::auth::AuthService;
+
+    const ADMIN_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn setup() -> (TotpService, User) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let admin = AuthService::new(db.clone())
+            .get_user_by_id(ADMIN_ID.parse().unwrap())
+            .await
+            .unwrap();
+        (TotpService::new(db, "RCP Admin"), admin)
+    }
+
+    fn start() -> DateTime<Utc> {
+        // The start of a time step
+        "2026-01-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn step(n: i64) -> Duration {
+        Duration::seconds(n * TOTP_STEP_SECS as i64)
+    }
+
+    fn code_at(service: &TotpService, secret: &str, at: DateTime<Utc>) -> String {
+        service
+            .totp(secret, "admin")
+            .unwrap()
+            .generate(at.timestamp() as u64)
+    }
+
+    /// Enrolls `user` and returns the secret and recovery codes.
+    async fn enroll(service: &TotpService, user: &User) -> (String, Vec<String>) {
+        let secret = service.begin_enrollment(user).await.unwrap().secret;
+        let code = code_at(service, &secret, start());
+        let recovery_codes = service
+            .confirm_enrollment(user, &code, start())
+            .await
+            .unwrap();
+        (secret, recovery_codes)
+    }
+
+    #[tokio::test]
+    async fn accepts_codes_within_one_step_of_skew() {
+        let (service, admin) = setup().await;
+        let (secret, _) = enroll(&service, &admin).await;
+        let now = start() + step(10);
+
+        for (offset, accepted) in [(-2, false), (2, false), (-1, true), (1, true)] {
+            let code = code_at(&service, &secret, now + step(offset));
+            assert_eq!(
+                service.verify(&admin, &code, now).await.unwrap(),
+                accepted,
+                "offset {}",
+                offset
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_replayed_and_older_codes() {
+        let (service, admin) = setup().await;
+        let (secret, _) = enroll(&service, &admin).await;
+
+        // The enrollment code cannot be used again
+        let enrolled = code_at(&service, &secret, start());
+        assert!(!service.verify(&admin, &enrolled, start()).await.unwrap());
+
+        let now = start() + step(5);
+        let current = code_at(&service, &secret, now);
+        assert!(service.verify(&admin, &current, now).await.unwrap());
+        assert!(!service.verify(&admin, &current, now).await.unwrap());
+
+        // A code from the previous step is within the skew but older than
+        // the one just accepted
+        let previous = code_at(&service, &secret, now - step(1));
+        assert!(!service.verify(&admin, &previous, now).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn recovery_codes_work_once() {
+        let (service, admin) = setup().await;
+        let (_, codes) = enroll(&service, &admin).await;
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        // Case and dashes do not matter
+        let typed = codes[0].to_uppercase().replace('-', "");
+        assert!(service.verify(&admin, &typed, start()).await.unwrap());
+        assert!(!service.verify(&admin, &codes[0], start()).await.unwrap());
+        assert!(service.verify(&admin, &codes[1], start()).await.unwrap());
+
+        let status = service.status(admin.id, &admin.role).await.unwrap();
+        assert_eq!(status.recovery_codes_remaining, 8);
+
+        // Regenerating invalidates the old codes
+        let fresh = service.regenerate_recovery_codes(admin.id).await.unwrap();
+        assert!(!service.verify(&admin, &codes[2], start()).await.unwrap());
+        assert!(service.verify(&admin, &fresh[0], start()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn challenges_run_out_of_attempts_and_expire() {
+        let (service, admin) = setup().await;
+
+        let (token, ttl) = service.create_challenge(admin.id, start()).await.unwrap();
+        for _ in 0..MAX_CHALLENGE_ATTEMPTS {
+            assert_eq!(
+                service.use_challenge(&token, start()).await.unwrap(),
+                admin.id
+            );
+        }
+        assert!(service.use_challenge(&token, start()).await.is_err());
+        // Dropped, not just refused once
+        assert!(service.use_challenge(&token, start()).await.is_err());
+
+        let (token, _) = service.create_challenge(admin.id, start()).await.unwrap();
+        let expired = start() + Duration::seconds(ttl);
+        assert!(service.use_challenge(&token, expired).await.is_err());
+        assert!(service
+            .use_challenge("not-a-challenge", start())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn enrollment_is_required_until_enabled() {
+        let (service, admin) = setup().await;
+        assert!(!service.enrollment_required(&admin).await.unwrap());
+
+        PermissionService::new(service.db.clone())
+            .set_requires_totp(&admin.role, true)
+            .await
+            .unwrap();
+        assert!(service.enrollment_required(&admin).await.unwrap());
+
+        // A pending enrollment is not enough
+        let secret = service.begin_enrollment(&admin).await.unwrap().secret;
+        assert!(service.enrollment_required(&admin).await.unwrap());
+
+        let code = code_at(&service, &secret, start());
+        service
+            .confirm_enrollment(&admin, &code, start())
+            .await
+            .unwrap();
+        assert!(!service.enrollment_required(&admin).await.unwrap());
+    }
+}
@@ -1,4 +1,3 @@
-use bcrypt::{hash, DEFAULT_COST};
 use chrono::Utc;
 use sqlx::{query, query_as};
 use uuid::Uuid;
@@ -6,9 +5,13 @@ use uuid::Uuid;
 use crate::{
     db::Database,
     error::{AppError, Result},
-    models::{CreateUser, UpdateUser, User, UserDb, UserInfo, UserInfoDb},
+    models::{CreateUser, UpdateUser, User, UserDb, UserInfoDb, UserList, UserListQuery},
+    services::password::hash_password,
 };
 
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
 pub struct UserService {
     db: Database,
 }
@@ -26,15 +29,14 @@ impl UserService {
             .await?;
 
         if existing.is_some() {
-            return Err(AppError::Validation(format!(
+            return Err(AppError::Conflict(format!(
                 "User with username '{}' already exists",
                 user.username
             )));
         }
 
         // Hash password
-        let password_hash = hash(&user.password, DEFAULT_COST)
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to hash password: {}", e)))?;
+        let password_hash = hash_password(&user.password)?;
 
         // Create new user
         let now = Utc::now();
@@ -56,19 +58,49 @@ impl UserService {
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
         .fetch_one(self.db.pool())
-        .await?;
+        .await
+        .map_err(|e| username_conflict(e, &user.username))?;
 
         Ok(created_user.into())
     }
 
-    pub async fn get_users(&self) -> Result<Vec<UserInfo>> {
+    pub async fn get_users(&self, filter: &UserListQuery) -> Result<UserList> {
+        let page = filter.page.unwrap_or(1).max(1);
+        let per_page = filter
+            .per_page
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let role = filter.role.as_ref().map(|r| r.to_string());
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE (?1 IS NULL OR role = ?1) AND (?2 IS NULL OR is_active = ?2)",
+        )
+        .bind(&role)
+        .bind(filter.is_active)
+        .fetch_one(self.db.pool())
+        .await?;
+
         let users = query_as::<_, UserInfoDb>(
-            "SELECT id, username, email, role, is_active FROM users ORDER BY username ASC",
+            r#"
+            SELECT id, username, email, role, is_active FROM users
+            WHERE (?1 IS NULL OR role = ?1) AND (?2 IS NULL OR is_active = ?2)
+            ORDER BY username ASC
+            LIMIT ?3 OFFSET ?4
+            "#,
         )
+        .bind(&role)
+        .bind(filter.is_active)
+        .bind(per_page as i64)
+        .bind((page as i64 - 1) * per_page as i64)
         .fetch_all(self.db.pool())
         .await?;
 
-        Ok(users.into_iter().map(|u| u.into()).collect())
+        Ok(UserList {
+            users: users.into_iter().map(|u| u.into()).collect(),
+            total,
+            page,
+            per_page,
+        })
     }
 
     pub async fn get_user(&self, id: Uuid) -> Result<User> {
@@ -76,8 +108,9 @@ impl UserService {
             "SELECT id, username, email, password_hash, role, is_active, created_at, updated_at FROM users WHERE id = ?"
         )
         .bind(id.to_string())
-        .fetch_one(self.db.pool())
-        .await?;
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
         Ok(user.into())
     }
@@ -97,14 +130,16 @@ impl UserService {
             RETURNING id, username, email, password_hash, role, is_active, created_at, updated_at
             "#,
         )
-        .bind(update.username)
+        .bind(&update.username)
         .bind(update.email)
         .bind(update.role.map(|r| r.to_string()))
         .bind(update.is_active)
         .bind(now.to_rfc3339())
         .bind(id.to_string())
-        .fetch_one(self.db.pool())
-        .await?;
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|e| username_conflict(e, update.username.as_deref().unwrap_or_default()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
         Ok(updated_user.into())
     }
@@ -122,3 +157,14 @@ impl UserService {
         Ok(())
     }
 }
+
+/// Maps a UNIQUE constraint violation on `users.username` to a conflict, so
+/// that two concurrent creates or a rename onto a taken name report 409.
+fn username_conflict(err: sqlx::Error, username: &str) -> AppError {
+    match err {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            AppError::Conflict(format!("User with username '{}' already exists", username))
+        }
+        other => other.into(),
+    }
+}
@@ -0,0 +1,398 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use futures_util::future::join_all;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use subtle::ConstantTimeEq;
+use tracing::debug;
+
+use crate::{
+    auth::bearer_token,
+    error::{AppError, Result},
+    models::{ServerStatus, SessionMetrics, SystemMetrics},
+    AppState,
+};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Upper bounds, in seconds, of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Name, help and value of a metric family read from a daemon's `T`
+type Family<T, V> = (&'static str, &'static str, fn(&T) -> V);
+
+/// How long fetched daemon state is reused by later scrapes
+const DAEMON_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Route label for requests no route matched (static files, 404s), so
+/// arbitrary paths cannot blow up the label cardinality
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Default)]
+struct RouteStats {
+    responses: BTreeMap<u16, u64>,
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    duration_sum: f64,
+    count: u64,
+}
+
+/// Counters kept by the backend itself; daemon state is fetched at scrape
+/// time instead, at most once per [`DAEMON_CACHE_TTL`].
+#[derive(Default)]
+pub struct Telemetry {
+    /// Keyed by (method, route template)
+    routes: Mutex<BTreeMap<(String, String), RouteStats>>,
+    /// Encoded daemon series and when they were fetched
+    daemon_cache: tokio::sync::Mutex<Option<(Instant, String)>>,
+    logins_succeeded: AtomicU64,
+    logins_failed: AtomicU64,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_login(&self, success: bool) {
+        let counter = if success {
+            &self.logins_succeeded
+        } else {
+            &self.logins_failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = routes
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+
+        *stats.responses.entry(status).or_default() += 1;
+        for (bucket, bound) in stats.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        stats.duration_sum += seconds;
+        stats.count += 1;
+    }
+
+    fn encode_http(&self, out: &mut String) {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+
+        family(
+            out,
+            "rcpadmin_http_requests",
+            "counter",
+            "HTTP requests handled, by route and response status.",
+        );
+        for ((method, route), stats) in routes.iter() {
+            for (status, count) in &stats.responses {
+                let _ = writeln!(
+                    out,
+                    "rcpadmin_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    escape(method),
+                    escape(route),
+                    status,
+                    count
+                );
+            }
+        }
+
+        family(
+            out,
+            "rcpadmin_http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency, by route.",
+        );
+        for ((method, route), stats) in routes.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape(method), escape(route));
+            for (count, bound) in stats.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "rcpadmin_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "rcpadmin_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, stats.count
+            );
+            let _ = writeln!(
+                out,
+                "rcpadmin_http_request_duration_seconds_sum{{{}}} {}",
+                labels, stats.duration_sum
+            );
+            let _ = writeln!(
+                out,
+                "rcpadmin_http_request_duration_seconds_count{{{}}} {}",
+                labels, stats.count
+            );
+        }
+    }
+
+    fn encode_logins(&self, out: &mut String) {
+        family(
+            out,
+            "rcpadmin_logins",
+            "counter",
+            "Login attempts, by outcome.",
+        );
+        let _ = writeln!(
+            out,
+            "rcpadmin_logins_total{{outcome=\"success\"}} {}",
+            self.logins_succeeded.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "rcpadmin_logins_total{{outcome=\"failure\"}} {}",
+            self.logins_failed.load(Ordering::Relaxed)
+        );
+    }
+}
+
+/// Counts requests and their latency per matched route template. Sits next
+/// to the `TraceLayer` in the global middleware stack.
+pub async fn track_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    state.telemetry.record_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_secs_f64(),
+    );
+
+    response
+}
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(scrape))
+}
+
+/// Prometheus scrape endpoint. Disabled unless `metrics.token` is set,
+/// since the session labels identify live users.
+async fn scrape(State(state): State<AppState>, headers: HeaderMap) -> Result<Response> {
+    let Some(expected) = &state.config.metrics.token else {
+        return Err(AppError::NotFound(
+            "Metrics endpoint is disabled; set metrics.token to enable it".to_string(),
+        ));
+    };
+    let token = bearer_token(&headers)?;
+    if !bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+        return Err(AppError::Auth("Invalid metrics token".to_string()));
+    }
+
+    let mut out = String::new();
+    state.telemetry.encode_http(&mut out);
+    state.telemetry.encode_logins(&mut out);
+    out.push_str(&cached_daemon_series(&state).await);
+    out.push_str("# EOF\n");
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], out).into_response())
+}
+
+/// [`encode_daemon`], reused for [`DAEMON_CACHE_TTL`]. Concurrent scrapes
+/// wait for the one already fetching instead of asking the daemons again.
+async fn cached_daemon_series(state: &AppState) -> String {
+    let mut cache = state.telemetry.daemon_cache.lock().await;
+    if let Some((fetched_at, series)) = cache.as_ref() {
+        if fetched_at.elapsed() < DAEMON_CACHE_TTL {
+            return series.clone();
+        }
+    }
+
+    let mut series = String::new();
+    encode_daemon(state, &mut series).await;
+    *cache = Some((Instant::now(), series.clone()));
+    series
+}
+
+/// State of every enabled daemon, fetched fresh on every scrape and labelled
+/// with the daemon's name. Series whose source is unavailable are left out,
+/// and `rcpdaemon_up` reports the outage.
+async fn encode_daemon(state: &AppState, out: &mut String) {
+    let daemons = state.daemons.enabled();
+    let scrapes = join_all(daemons.iter().map(|daemon| async move {
+        tokio::join!(
+            daemon.client.get_status(),
+            daemon.client.get_system_metrics(),
+            daemon.client.get_sessions()
+        )
+    }))
+    .await;
+
+    let mut statuses = Vec::new();
+    let mut systems = Vec::new();
+    let mut sessions = Vec::new();
+    for (daemon, (status, system, daemon_sessions)) in daemons.iter().zip(scrapes) {
+        let label = escape(&daemon.name);
+        match status {
+            Ok(status) => statuses.push((label.clone(), Some(status))),
+            Err(e) => {
+                debug!(
+                    "Status of RCP daemon '{}' unavailable for scrape: {}",
+                    daemon.name, e
+                );
+                statuses.push((label.clone(), None));
+            }
+        }
+        match system {
+            Ok(metrics) => systems.push((label.clone(), metrics)),
+            Err(e) => debug!(
+                "System metrics of RCP daemon '{}' unavailable for scrape: {}",
+                daemon.name, e
+            ),
+        }
+        match daemon_sessions {
+            Ok(daemon_sessions) => {
+                sessions.extend(daemon_sessions.into_iter().map(|s| (label.clone(), s)))
+            }
+            Err(e) => debug!(
+                "Sessions of RCP daemon '{}' unavailable for scrape: {}",
+                daemon.name, e
+            ),
+        }
+    }
+
+    family(
+        out,
+        "rcpdaemon_up",
+        "gauge",
+        "Whether the RCP daemon answered the last status request.",
+    );
+    for (daemon, status) in &statuses {
+        let _ = writeln!(
+            out,
+            "rcpdaemon_up{{daemon=\"{}\"}} {}",
+            daemon,
+            u8::from(status.is_some())
+        );
+    }
+
+    let answered: Vec<(&String, &ServerStatus)> = statuses
+        .iter()
+        .filter_map(|(daemon, status)| Some((daemon, status.as_ref()?)))
+        .collect();
+    let status_gauges: [Family<ServerStatus, u64>; 2] = [
+        (
+            "rcpdaemon_active_sessions",
+            "Active sessions reported by the RCP daemon.",
+            |s| s.active_sessions.into(),
+        ),
+        (
+            "rcpdaemon_uptime_seconds",
+            "Uptime of the RCP daemon.",
+            |s| s.uptime,
+        ),
+    ];
+    if !answered.is_empty() {
+        for (name, help, value) in status_gauges {
+            family(out, name, "gauge", help);
+            for (daemon, status) in &answered {
+                let _ = writeln!(out, "{}{{daemon=\"{}\"}} {}", name, daemon, value(status));
+            }
+        }
+    }
+
+    let system_gauges: [Family<SystemMetrics, f64>; 5] = [
+        ("rcpdaemon_cpu_usage_percent", "Host CPU usage.", |m| {
+            m.cpu_usage
+        }),
+        ("rcpdaemon_memory_usage_bytes", "Host memory in use.", |m| {
+            m.memory_usage as f64
+        }),
+        (
+            "rcpdaemon_memory_total_bytes",
+            "Host memory installed.",
+            |m| m.total_memory as f64,
+        ),
+        (
+            "rcpdaemon_disk_usage_bytes",
+            "Host disk space in use.",
+            |m| m.disk_usage as f64,
+        ),
+        (
+            "rcpdaemon_disk_total_bytes",
+            "Host disk space available.",
+            |m| m.total_disk as f64,
+        ),
+    ];
+    if !systems.is_empty() {
+        for (name, help, value) in system_gauges {
+            family(out, name, "gauge", help);
+            for (daemon, metrics) in &systems {
+                let _ = writeln!(out, "{}{{daemon=\"{}\"}} {}", name, daemon, value(metrics));
+            }
+        }
+    }
+
+    let counters: [Family<SessionMetrics, u64>; 2] = [
+        (
+            "rcpdaemon_session_network_receive_bytes",
+            "Bytes received by a session.",
+            |m| m.network_rx,
+        ),
+        (
+            "rcpdaemon_session_network_transmit_bytes",
+            "Bytes sent by a session.",
+            |m| m.network_tx,
+        ),
+    ];
+    for (name, help, value) in counters {
+        family(out, name, "counter", help);
+        for (daemon, session) in &sessions {
+            let _ = writeln!(
+                out,
+                "{}_total{{daemon=\"{}\",session_id=\"{}\",application_id=\"{}\"}} {}",
+                name,
+                daemon,
+                session.id,
+                escape(&session.application_id),
+                value(&session.metrics)
+            );
+        }
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+/// Escapes a label value per the OpenMetrics text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,190 @@
+use anyhow::{bail, Context, Result};
+use axum::{extract::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use rustls_pemfile::Item;
+use std::{
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+use tracing::{debug, error};
+
+use crate::config::TlsConfig;
+
+/// Clients that connect but never finish the handshake are dropped after this
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The rustls settings in use, swapped out whole when certificates are
+/// reloaded. New connections pick up the current settings.
+#[derive(Clone)]
+pub struct TlsSettings {
+    tls: TlsConfig,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl TlsSettings {
+    /// Loads the certificate, key and client CA files. Expects a config that
+    /// passed `Config::validate`.
+    pub fn load(tls: &TlsConfig) -> Result<Self> {
+        Ok(Self {
+            tls: tls.clone(),
+            current: Arc::new(RwLock::new(Arc::new(server_config(tls)?))),
+        })
+    }
+
+    /// Re-reads the files; on failure the current settings stay in place.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub fn reload(&self) -> Result<()> {
+        let config = server_config(&self.tls)?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+}
+
+/// Reloads certificates on SIGHUP, so renewed certificates are picked up
+/// without a restart. Connections already open keep their old certificate.
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(settings: TlsSettings) -> Result<()> {
+    let mut hangups = signal(SignalKind::hangup()).context("Failed to listen for SIGHUP")?;
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            let settings = settings.clone();
+            match tokio::task::spawn_blocking(move || settings.reload()).await {
+                Ok(Ok(())) => tracing::info!("Reloaded TLS certificates"),
+                Ok(Err(e)) => error!("Keeping current TLS certificates, reload failed: {:#}", e),
+                Err(e) => error!("TLS reload task failed: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Serves `app` over HTTPS (HTTP/1.1 and HTTP/2), with the caller's address
+/// available as `ConnectInfo<SocketAddr>` like `axum::serve` provides.
+pub async fn serve(listener: TcpListener, settings: TlsSettings, app: Router) -> Result<()> {
+    let mut make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    loop {
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // Usually out of file descriptors; back off instead of spinning
+                error!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let acceptor = settings.acceptor();
+        let service = make_service
+            .call(remote_addr)
+            .await
+            .unwrap_or_else(|e| match e {});
+
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    // Includes clients without an acceptable certificate under mTLS
+                    Ok(Err(e)) => {
+                        debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        debug!("TLS handshake with {} timed out", remote_addr);
+                        return;
+                    }
+                };
+
+            let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
+                service.clone().call(request)
+            });
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), hyper_service)
+                .await
+            {
+                debug!("Connection from {} closed with error: {}", remote_addr, e);
+            }
+        });
+    }
+}
+
+fn server_config(tls: &TlsConfig) -> Result<ServerConfig> {
+    let (Some(cert_path), Some(key_path)) = (&tls.cert_path, &tls.key_path) else {
+        bail!("tls.cert_path and tls.key_path are required when TLS is enabled");
+    };
+    let certs = load_certs(cert_path)?;
+    let key = load_key(key_path)?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &tls.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots
+                    .add(&cert)
+                    .with_context(|| format!("Invalid CA certificate in {}", ca_path.display()))?;
+            }
+            // All or nothing: a connection without a certificate from these
+            // CAs is refused, and the certificate is not mapped to a user, so
+            // requests still authenticate with a token or API key
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_single_cert(certs, key).with_context(|| {
+        format!(
+            "{} does not match {}",
+            key_path.display(),
+            cert_path.display()
+        )
+    })?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("Invalid PEM in {}", path.display()))?;
+    if certs.is_empty() {
+        bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Accepts PKCS#8, PKCS#1 (RSA) and SEC1 (EC) keys; the first one wins.
+fn load_key(path: &Path) -> Result<PrivateKey> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("Invalid PEM in {}", path.display()))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .with_context(|| format!("No private key found in {}", path.display()))
+}
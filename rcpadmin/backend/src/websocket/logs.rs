@@ -0,0 +1,101 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
+    response::Response,
+    Extension,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info};
+
+use crate::{
+    auth::WsAccess,
+    models::{LogEntry, LogQuery},
+    AppState,
+};
+
+/// Matching entries sent before the live tail when the query gives no `limit`
+const DEFAULT_BACKLOG: usize = 100;
+
+/// Live tail of the log buffer. Takes the same filters as
+/// `/api/v1/system/logs`; `limit` sets how many past entries come first.
+pub async fn ws_logs_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Extension(access): Extension<WsAccess>,
+    Query(mut query): Query<LogQuery>,
+) -> Response {
+    info!("New WebSocket connection for log tail");
+
+    query.limit = Some(query.limit.unwrap_or(DEFAULT_BACKLOG));
+    ws.on_upgrade(
+        move |socket| async move { handle_logs_socket(socket, state, access, query).await },
+    )
+}
+
+async fn handle_logs_socket(socket: WebSocket, state: AppState, access: WsAccess, query: LogQuery) {
+    let (mut sender, mut receiver) = socket.split();
+    let revoked = access.revoked();
+    tokio::pin!(revoked);
+
+    // Subscribe before reading the backlog so nothing logged in between is
+    // lost; duplicates are dropped by comparing ids
+    let mut updates = state.logs.subscribe();
+    let mut last_sent = 0;
+
+    for entry in state.logs.query(&query).entries {
+        last_sent = entry.id;
+        if !send_entry(&mut sender, &entry).await {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(entry) if entry.id > last_sent && query.matches(&entry) => {
+                    last_sent = entry.id;
+                    if !send_entry(&mut sender, &entry).await {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Log tail lagged, skipped {} entries", skipped)
+                }
+                Err(RecvError::Closed) => break,
+            },
+            msg = receiver.next() => match msg {
+                Some(Ok(Message::Ping(payload))) => {
+                    if sender.send(Message::Pong(payload)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {} // Ignore other messages
+            },
+            reason = &mut revoked => {
+                super::close_revoked(&mut sender, reason).await;
+                break;
+            }
+        }
+    }
+
+    info!("Log tail WebSocket connection closed");
+}
+
+/// Returns `false` once the client is gone.
+async fn send_entry(
+    sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    entry: &LogEntry,
+) -> bool {
+    match serde_json::to_string(entry) {
+        Ok(json) => sender.send(Message::Text(json)).await.is_ok(),
+        Err(e) => {
+            error!("Failed to serialize log entry: {}", e);
+            true
+        }
+    }
+}
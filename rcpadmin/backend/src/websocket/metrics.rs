@@ -0,0 +1,198 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+    Extension,
+};
+use futures_util::{future::join_all, SinkExt, StreamExt};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::{auth::WsAccess, fleet::DaemonHandle, models::SystemMetrics, AppState};
+
+// Channel size for broadcasting updates to connected clients
+const CHANNEL_SIZE: usize = 32;
+
+/// How often the shared poller fetches metrics from the daemon
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Send interval for sockets that have not asked for a different one
+const DEFAULT_SEND_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_SEND_INTERVAL: Duration = Duration::from_secs(300);
+
+pub type MetricsSender = broadcast::Sender<SystemMetrics>;
+
+/// Messages a client may send over the metrics socket.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Change how often this socket receives metrics
+    Subscribe { interval_secs: u64 },
+}
+
+/// A metrics channel for each daemon that sockets are watching.
+pub struct MetricsHub {
+    channels: Mutex<HashMap<Uuid, MetricsSender>>,
+}
+
+impl MetricsHub {
+    pub fn new() -> Self {
+        Self {
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self, daemon_id: Uuid) -> broadcast::Receiver<SystemMetrics> {
+        self.channels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(daemon_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_SIZE).0)
+            .subscribe()
+    }
+
+    /// Channels that still have subscribers; the others are dropped.
+    fn watched(&self) -> Vec<(Uuid, MetricsSender)> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .iter()
+            .map(|(id, sender)| (*id, sender.clone()))
+            .collect()
+    }
+}
+
+impl Default for MetricsHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Polls each watched daemon for system metrics and publishes them to its
+/// metrics sockets, so a daemon sees one poller no matter how many browsers
+/// are open. Daemons nobody is watching are not polled.
+pub fn spawn_metrics_poller(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let polls = state
+                .metrics
+                .watched()
+                .into_iter()
+                .filter_map(|(id, sender)| Some((state.daemons.get(id).ok()?, sender)))
+                .map(|(daemon, sender)| async move {
+                    match daemon.client.get_system_metrics().await {
+                        Ok(metrics) => {
+                            // Only fails when the last subscriber just went away
+                            let _ = sender.send(metrics);
+                        }
+                        Err(e) => warn!(
+                            "Failed to poll system metrics of RCP daemon '{}': {}",
+                            daemon.name, e
+                        ),
+                    }
+                });
+            join_all(polls).await;
+        }
+    });
+}
+
+pub async fn ws_metrics_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Extension(access): Extension<WsAccess>,
+) -> Response {
+    info!(
+        "New WebSocket connection for metrics of RCP daemon '{}'",
+        daemon.name
+    );
+
+    ws.on_upgrade(move |socket| async move {
+        handle_metrics_socket(socket, state, access, daemon.id).await
+    })
+}
+
+async fn handle_metrics_socket(
+    socket: WebSocket,
+    state: AppState,
+    access: WsAccess,
+    daemon_id: Uuid,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let revoked = access.revoked();
+    tokio::pin!(revoked);
+    let mut updates = state.metrics.subscribe(daemon_id);
+
+    let mut send_interval = DEFAULT_SEND_INTERVAL;
+    let mut ticker = tokio::time::interval(send_interval);
+    let mut latest: Option<SystemMetrics> = None;
+
+    // A single loop owns both halves, so pings can be answered directly
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(metrics) => latest = Some(metrics),
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Metrics socket lagged, skipped {} updates", skipped)
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => {
+                let Some(metrics) = latest.take() else {
+                    continue;
+                };
+
+                // Convert metrics to JSON
+                let json = match serde_json::to_string(&metrics) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("Failed to serialize metrics: {}", e);
+                        continue;
+                    }
+                };
+
+                if sender.send(Message::Text(json)).await.is_err() {
+                    // Client disconnected
+                    break;
+                }
+            }
+            msg = receiver.next() => match msg {
+                Some(Ok(Message::Ping(payload))) => {
+                    if sender.send(Message::Pong(payload)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Subscribe { interval_secs }) => {
+                        send_interval = Duration::from_secs(interval_secs)
+                            .clamp(POLL_INTERVAL, MAX_SEND_INTERVAL);
+                        ticker = tokio::time::interval(send_interval);
+                        debug!("Metrics socket now receives updates every {:?}", send_interval);
+                    }
+                    Err(e) => debug!("Ignoring unrecognised metrics socket message: {}", e),
+                },
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {} // Ignore other messages
+            },
+            reason = &mut revoked => {
+                super::close_revoked(&mut sender, reason).await;
+                break;
+            }
+        }
+    }
+
+    info!("WebSocket connection closed");
+}
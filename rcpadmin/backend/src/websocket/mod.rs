@@ -0,0 +1,68 @@
+use axum::{
+    extract::ws::{close_code, CloseFrame, Message, WebSocket},
+    middleware::from_fn_with_state,
+    routing::get,
+    Router,
+};
+use futures_util::{stream::SplitSink, SinkExt};
+use tracing::info;
+
+pub mod logs;
+pub mod metrics;
+pub mod sessions;
+
+pub use metrics::{spawn_metrics_poller, MetricsHub};
+pub use sessions::{spawn_session_watcher, SessionEventHub};
+
+use crate::{auth::require_permission, error::AppError, models::Permission, AppState};
+
+/// WebSocket routes; the caller nests them behind `protect_ws`.
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/logs",
+            get(logs::ws_logs_handler).route_layer(from_fn_with_state(
+                Permission::AuditRead,
+                require_permission,
+            )),
+        )
+        .route(
+            "/sessions",
+            get(sessions::ws_sessions_handler).route_layer(from_fn_with_state(
+                Permission::SessionsRead,
+                require_permission,
+            )),
+        )
+}
+
+/// WebSocket routes of one daemon, nested under `/daemons/:daemon_id`. The
+/// caller layers them with [`crate::fleet::resolve_daemon`].
+pub fn create_daemon_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/metrics",
+            get(metrics::ws_metrics_handler).route_layer(from_fn_with_state(
+                Permission::SystemRead,
+                require_permission,
+            )),
+        )
+        .route(
+            "/sessions",
+            get(sessions::ws_daemon_sessions_handler).route_layer(from_fn_with_state(
+                Permission::SessionsRead,
+                require_permission,
+            )),
+        )
+}
+
+/// Closes a socket whose credentials were revoked, see
+/// [`crate::auth::WsAccess`]. Clients should reconnect with a fresh token.
+async fn close_revoked(sender: &mut SplitSink<WebSocket, Message>, reason: AppError) {
+    info!("Closing WebSocket, access revoked: {}", reason);
+    let _ = sender
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: "Access revoked".into(),
+        })))
+        .await;
+}
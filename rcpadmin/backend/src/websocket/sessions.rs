@@ -0,0 +1,342 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
+    response::Response,
+    Extension,
+};
+use chrono::Utc;
+use futures_util::{future::join_all, SinkExt, StreamExt};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    auth::WsAccess,
+    fleet::DaemonHandle,
+    models::{Session, SessionEvent, SessionEventKind, SessionStatus},
+    services::launch_history::LaunchHistoryService,
+    AppState,
+};
+
+const CHANNEL_SIZE: usize = 256;
+/// Events kept for clients resuming with `last_event_id`
+const HISTORY_SIZE: usize = 1000;
+/// How often the watcher diffs each daemon's session list
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Sent instead of a replay when the requested events are no longer
+/// available; the client should reload its session lists.
+const RESYNC_MESSAGE: &str = r#"{"type":"resync"}"#;
+
+/// Fan-out of session events with a bounded history for resuming clients.
+pub struct SessionEventHub {
+    sender: broadcast::Sender<SessionEvent>,
+    inner: Mutex<HubInner>,
+}
+
+struct HubInner {
+    history: VecDeque<SessionEvent>,
+    next_id: u64,
+}
+
+impl SessionEventHub {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_SIZE).0,
+            inner: Mutex::new(HubInner {
+                history: VecDeque::with_capacity(HISTORY_SIZE),
+                // Start from the clock so ids handed out before a restart are
+                // always older than the current history and force a resync
+                next_id: Utc::now().timestamp_millis().max(1) as u64,
+            }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, daemon_id: Uuid, kind: SessionEventKind, session: Session) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        let event = SessionEvent {
+            id: inner.next_id,
+            timestamp: Utc::now(),
+            daemon_id,
+            kind,
+            session,
+        };
+        inner.next_id += 1;
+
+        if inner.history.len() == HISTORY_SIZE {
+            inner.history.pop_front();
+        }
+        inner.history.push_back(event.clone());
+
+        // Sent under the lock so live delivery order matches event ids
+        let _ = self.sender.send(event);
+    }
+
+    /// Events after `last_id`, or `None` if some of them are no longer
+    /// retained (or `last_id` was issued by a previous process).
+    pub fn since(&self, last_id: u64) -> Option<Vec<SessionEvent>> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let oldest = inner.history.front().map(|e| e.id).unwrap_or(inner.next_id);
+
+        if last_id.saturating_add(1) < oldest || last_id >= inner.next_id {
+            return None;
+        }
+
+        Some(
+            inner
+                .history
+                .iter()
+                .filter(|e| e.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl Default for SessionEventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Polls the session list of every enabled daemon and publishes the
+/// differences between consecutive snapshots as [`SessionEvent`]s.
+pub fn spawn_session_watcher(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // A daemon's first snapshot is the baseline; clients load it over
+        // REST. A daemon that does not answer keeps its last snapshot.
+        let mut known: HashMap<Uuid, HashMap<Uuid, Session>> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let daemons = state.daemons.enabled();
+            let polls = join_all(daemons.iter().map(|daemon| daemon.client.get_sessions())).await;
+            known.retain(|id, _| daemons.iter().any(|d| d.id == *id));
+
+            for (daemon, sessions) in daemons.iter().zip(polls) {
+                let sessions = match sessions {
+                    Ok(sessions) => sessions,
+                    Err(e) => {
+                        debug!(
+                            "Failed to poll sessions of RCP daemon '{}': {}",
+                            daemon.name, e
+                        );
+                        continue;
+                    }
+                };
+
+                let current: HashMap<Uuid, Session> =
+                    sessions.into_iter().map(|s| (s.id, s)).collect();
+
+                if let Some(previous) = known.get(&daemon.id) {
+                    publish_changes(&state.session_events, daemon.id, previous, &current);
+                    close_ended_launches(&state, previous, &current).await;
+                }
+                known.insert(daemon.id, current);
+            }
+        }
+    });
+}
+
+fn publish_changes(
+    hub: &SessionEventHub,
+    daemon_id: Uuid,
+    previous: &HashMap<Uuid, Session>,
+    current: &HashMap<Uuid, Session>,
+) {
+    for (id, session) in current {
+        match previous.get(id) {
+            None => hub.publish(daemon_id, SessionEventKind::Created, session.clone()),
+            Some(old) if old.status != session.status => hub.publish(
+                daemon_id,
+                SessionEventKind::StatusChanged {
+                    previous: old.status.clone(),
+                },
+                session.clone(),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    for (id, old) in previous {
+        if !current.contains_key(id) {
+            let mut session = old.clone();
+            session.status = SessionStatus::Terminated;
+            hub.publish(daemon_id, SessionEventKind::Removed, session);
+        }
+    }
+}
+
+/// Ends the launch history entries of sessions that were removed or
+/// terminated since the previous snapshot.
+async fn close_ended_launches(
+    state: &AppState,
+    previous: &HashMap<Uuid, Session>,
+    current: &HashMap<Uuid, Session>,
+) {
+    let history = LaunchHistoryService::new(state.db.clone());
+
+    for (id, old) in previous {
+        let ended = match current.get(id) {
+            None => true,
+            Some(session) => {
+                session.status == SessionStatus::Terminated
+                    && old.status != SessionStatus::Terminated
+            }
+        };
+
+        if ended {
+            if let Err(e) = history.record_session_end(*id, Utc::now()).await {
+                warn!("Failed to record end of session {}: {}", id, e);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionStreamQuery {
+    /// Id of the last event the client saw before reconnecting
+    pub last_event_id: Option<u64>,
+}
+
+/// Session events of the whole fleet.
+pub async fn ws_sessions_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Extension(access): Extension<WsAccess>,
+    Query(query): Query<SessionStreamQuery>,
+) -> Response {
+    info!("New WebSocket connection for session events");
+
+    ws.on_upgrade(move |socket| async move {
+        handle_sessions_socket(socket, state, access, query.last_event_id, None).await
+    })
+}
+
+/// Session events of one daemon.
+pub async fn ws_daemon_sessions_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Extension(daemon): Extension<Arc<DaemonHandle>>,
+    Extension(access): Extension<WsAccess>,
+    Query(query): Query<SessionStreamQuery>,
+) -> Response {
+    info!(
+        "New WebSocket connection for session events of RCP daemon '{}'",
+        daemon.name
+    );
+
+    ws.on_upgrade(move |socket| async move {
+        handle_sessions_socket(socket, state, access, query.last_event_id, Some(daemon.id)).await
+    })
+}
+
+/// Streams events, only those of `daemon_id` if given. Event ids are shared
+/// by all daemons, so a filtered stream has gaps.
+async fn handle_sessions_socket(
+    socket: WebSocket,
+    state: AppState,
+    access: WsAccess,
+    last_event_id: Option<u64>,
+    daemon_id: Option<Uuid>,
+) {
+    let wanted = |event: &SessionEvent| daemon_id.is_none_or(|id| event.daemon_id == id);
+
+    let (mut sender, mut receiver) = socket.split();
+    let revoked = access.revoked();
+    tokio::pin!(revoked);
+
+    // Subscribe before replaying so nothing published in between is lost;
+    // duplicates are dropped by comparing ids
+    let mut updates = state.session_events.subscribe();
+    let mut last_sent = 0;
+
+    if let Some(last_id) = last_event_id {
+        let replay = match state.session_events.since(last_id) {
+            Some(events) => events,
+            None => {
+                if sender
+                    .send(Message::Text(RESYNC_MESSAGE.into()))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                Vec::new()
+            }
+        };
+
+        for event in replay.iter().filter(|e| wanted(e)) {
+            last_sent = event.id;
+            if !send_event(&mut sender, event).await {
+                return;
+            }
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(event) if event.id > last_sent && wanted(&event) => {
+                    last_sent = event.id;
+                    if !send_event(&mut sender, &event).await {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Session socket lagged, skipped {} events", skipped);
+                    if sender.send(Message::Text(RESYNC_MESSAGE.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+            msg = receiver.next() => match msg {
+                Some(Ok(Message::Ping(payload))) => {
+                    if sender.send(Message::Pong(payload)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {} // Ignore other messages
+            },
+            reason = &mut revoked => {
+                super::close_revoked(&mut sender, reason).await;
+                break;
+            }
+        }
+    }
+
+    info!("Session events WebSocket connection closed");
+}
+
+/// Returns `false` once the client is gone.
+async fn send_event(
+    sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    event: &SessionEvent,
+) -> bool {
+    match serde_json::to_string(event) {
+        Ok(json) => sender.send(Message::Text(json)).await.is_ok(),
+        Err(e) => {
+            error!("Failed to serialize session event: {}", e);
+            true
+        }
+    }
+}